@@ -0,0 +1,148 @@
+//! Response body analysis with a set of signatures.
+//!
+//! A signature is a named pattern for something a tester wants to know about, like a stack trace,
+//! an SQL error, a directory listing or a leaked key. When analysis is turned on, the body of every
+//! response is checked against every signature and the matches are kept with the result. The
+//! built-in signatures are in `signatures.toml`, and a file in the same format can be used instead.
+
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use std::fs;
+
+/// The built-in signatures, used when analysis is turned on without a signatures file.
+pub const DEFAULT_SIGNATURES: &str = include_str!("../signatures.toml");
+
+/// Only this many bytes from the start of a body are analyzed.
+pub const MAX_ANALYZED_BYTES: usize = 1024 * 1024;
+
+/// The most characters of a match kept as a sample.
+pub const MAX_SAMPLE_CHARS: usize = 80;
+
+/// A signature that matched a response body.
+#[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct BodyFinding {
+    // The name of the signature that matched.
+    pub signature: String,
+
+    // The text that matched, cut down to `MAX_SAMPLE_CHARS` characters.
+    pub sample: String,
+}
+
+/// A named pattern to look for in response bodies. It is sent to distributed workers with the
+/// pattern as its source text.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Signature {
+    pub name: String,
+
+    #[serde(with = "pattern_source")]
+    pub pattern: Regex,
+}
+
+impl PartialEq for Signature {
+    fn eq(&self, other: &Self) -> bool {
+        self.name == other.name && self.pattern.as_str() == other.pattern.as_str()
+    }
+}
+
+/// Patterns are sent as the text they were compiled from, and compiled again when they arrive.
+mod pattern_source {
+    use regex::Regex;
+    use serde::de::Error;
+    use serde::{Deserialize, Deserializer, Serializer};
+
+    pub fn serialize<S: Serializer>(pattern: &Regex, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(pattern.as_str())
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Regex, D::Error> {
+        Regex::new(&String::deserialize(deserializer)?).map_err(D::Error::custom)
+    }
+}
+
+/// The signatures every response body is checked against.
+#[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SignatureSet {
+    pub signatures: Vec<Signature>,
+}
+
+/// The layout of a signatures file.
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct SignatureFile {
+    signatures: Vec<SignatureEntry>,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct SignatureEntry {
+    name: String,
+    pattern: String,
+}
+
+impl SignatureSet {
+    /// Returns the built-in signatures.
+    pub fn builtin() -> Self {
+        // The built-in file is checked by the tests, so it always parses.
+        SignatureSet::parse(DEFAULT_SIGNATURES).unwrap()
+    }
+
+    /// Parses a signatures file from a string of TOML. Returns an error if the file or one of
+    /// its patterns is invalid.
+    pub fn parse(contents: &str) -> Result<Self, String> {
+        let file: SignatureFile = toml::from_str(contents)
+            .map_err(|err| format!("Could not parse signatures file: {}", err))?;
+        let mut signatures = Vec::new();
+        for entry in file.signatures {
+            signatures.push(Signature {
+                pattern: Regex::new(&entry.pattern)
+                    .map_err(|err| format!("Signature `{}`: {}", entry.name, err))?,
+                name: entry.name,
+            });
+        }
+        Ok(SignatureSet { signatures })
+    }
+
+    /// Reads and parses the signatures file at `path`.
+    pub fn from_file(path: &str) -> Result<Self, String> {
+        let contents = fs::read_to_string(path)
+            .map_err(|err| format!("Could not read signatures file {}: {}", path, err))?;
+        SignatureSet::parse(&contents)
+    }
+
+    /// Checks `body` against every signature and returns one finding for each signature that
+    /// matched, with the first match as its sample.
+    pub fn scan(&self, body: &str) -> Vec<BodyFinding> {
+        let body = truncate(body, MAX_ANALYZED_BYTES);
+        self.signatures
+            .iter()
+            .filter_map(|signature| {
+                signature.pattern.find(body).map(|matched| BodyFinding {
+                    signature: signature.name.clone(),
+                    sample: matched.as_str().chars().take(MAX_SAMPLE_CHARS).collect(),
+                })
+            })
+            .collect()
+    }
+}
+
+/// Returns the longest start of `text` that is at most `max` bytes long and ends on a character
+/// boundary.
+fn truncate(text: &str, max: usize) -> &str {
+    if text.len() <= max {
+        return text;
+    }
+    let mut end = max;
+    while !text.is_char_boundary(end) {
+        end -= 1;
+    }
+    &text[..end]
+}
+
+/// Formats the names of the signatures in `findings` for a single line of terminal output.
+pub fn finding_names(findings: &[BodyFinding]) -> String {
+    findings
+        .iter()
+        .map(|finding| finding.signature.as_str())
+        .collect::<Vec<&str>>()
+        .join(", ")
+}
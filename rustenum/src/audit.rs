@@ -0,0 +1,146 @@
+//! An append-only audit log of every request a scan sends.
+//!
+//! Engagements often need proof of exactly what was sent to a target and when. When an audit log
+//! is set, the scan engine appends one line of JSON to it for every request it sends, including
+//! retries and requests whose response was ignored. Each line has the time the request was sent,
+//! the method, the full url, a hash of the headers that were sent, and the status code and size
+//! of the response. The file is only ever appended to, so the log of earlier scans is kept.
+
+use reqwest::header::HeaderMap;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::fs::{self, File, OpenOptions};
+use std::io::Write;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Mutex;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// A single request in the audit log.
+#[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct AuditEntry {
+    // When the request was sent, in milliseconds since the Unix epoch.
+    pub timestamp_ms: u64,
+
+    pub method: String,
+
+    // The full url that was requested.
+    pub url: String,
+
+    // The SHA-256 hash of every header that was sent, as 64 hex digits. The headers are hashed as
+    // sorted "name: value" lines with lowercase names, so the same headers always hash the same.
+    pub headers_hash: String,
+
+    // Which attempt at the request this was, starting at 1.
+    pub attempt: u32,
+
+    // The HTTP status code of the response. None if there was no response.
+    pub status: Option<u16>,
+
+    // The size of the response body in bytes. None if the body wasn't read, like for a response
+    // that is retried, or if there was no response.
+    pub bytes: Option<u64>,
+
+    // Why there was no response.
+    pub error: Option<String>,
+}
+
+/// The audit log file a scan appends to.
+#[derive(Debug)]
+pub struct AuditLog {
+    // The path of the file, for error messages.
+    path: String,
+
+    file: Mutex<File>,
+
+    // The headers the client sends with every request, which are hashed along with each
+    // request's own headers.
+    default_headers: HeaderMap,
+
+    // The number of entries that couldn't be written.
+    failures: AtomicUsize,
+}
+
+impl AuditLog {
+    /// Opens the audit log at `path` for appending, creating it if it doesn't exist. The
+    /// `default_headers` are the headers the client sends with every request.
+    pub fn open(path: &str, default_headers: HeaderMap) -> Result<Self, String> {
+        let file = OpenOptions::new()
+            .append(true)
+            .create(true)
+            .open(path)
+            .map_err(|err| format!("Could not open the audit log {}: {}", path, err))?;
+        Ok(AuditLog {
+            path: path.to_string(),
+            file: Mutex::new(file),
+            default_headers,
+            failures: AtomicUsize::new(0),
+        })
+    }
+
+    /// Returns the path of the audit log.
+    pub fn path(&self) -> &str {
+        &self.path
+    }
+
+    /// Returns the number of entries that couldn't be written to the log.
+    pub fn failures(&self) -> usize {
+        self.failures.load(Ordering::SeqCst)
+    }
+
+    /// Hashes the headers sent with a request with SHA-256: the client's default headers, replaced
+    /// by the `request_headers` with the same name.
+    pub fn headers_hash(&self, request_headers: &HeaderMap) -> String {
+        let mut headers = self.default_headers.clone();
+        for (name, value) in request_headers {
+            headers.insert(name.clone(), value.clone());
+        }
+        let mut lines: Vec<String> = headers
+            .iter()
+            .map(|(name, value)| {
+                format!(
+                    "{}: {}\n",
+                    name.as_str().to_lowercase(),
+                    String::from_utf8_lossy(value.as_bytes())
+                )
+            })
+            .collect();
+        lines.sort();
+        Sha256::digest(lines.concat().as_bytes())
+            .iter()
+            .map(|byte| format!("{:02x}", byte))
+            .collect()
+    }
+
+    /// Appends `entry` to the log as a line of JSON. The write is counted as a failure if it
+    /// doesn't succeed, so the scan can report it.
+    pub fn record(&self, entry: &AuditEntry) {
+        let written = serde_json::to_string(entry).ok().and_then(|line| {
+            let mut file = self.file.lock().ok()?;
+            file.write_all(format!("{}\n", line).as_bytes()).ok()
+        });
+        if written.is_none() {
+            self.failures.fetch_add(1, Ordering::SeqCst);
+        }
+    }
+}
+
+/// Returns the current time in milliseconds since the Unix epoch.
+pub fn now_ms() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|since| since.as_millis() as u64)
+        .unwrap_or(0)
+}
+
+/// Reads every entry of the audit log at `path`.
+pub fn read_audit_log(path: &str) -> Result<Vec<AuditEntry>, String> {
+    let contents = fs::read_to_string(path)
+        .map_err(|err| format!("Could not read the audit log {}: {}", path, err))?;
+    contents
+        .lines()
+        .map(|line| {
+            serde_json::from_str(line)
+                .map_err(|err| format!("Could not parse the audit log {}: {}", path, err))
+        })
+        .collect()
+}
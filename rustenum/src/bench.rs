@@ -0,0 +1,125 @@
+//! A built-in throughput benchmark for the scan engine.
+//!
+//! The benchmark starts a small HTTP server on the loopback interface, scans it with a generated
+//! wordlist through `Scanner::run`, and reports how many requests per second were answered. The
+//! server speaks both HTTP/1.1 and HTTP/2 with prior knowledge, so the client settings in a
+//! `ScanConfig` can be compared against each other without touching a real target.
+
+use crate::config::ScanConfig;
+use crate::{Scanner, Wordlist};
+use colored::*;
+use hyper::service::{make_service_fn, service_fn};
+use hyper::{Body, Response, Server};
+use indicatif::ProgressBar;
+use serde::{Deserialize, Serialize};
+use std::convert::Infallible;
+use std::net::SocketAddr;
+use std::sync::Arc;
+use std::time::Instant;
+
+/// The number of requests sent by a benchmark when none is given.
+pub const DEFAULT_BENCH_REQUESTS: usize = 5000;
+
+/// The body of every response sent by the benchmark server.
+const BENCH_BODY: &str = "rustenum benchmark";
+
+/// The outcome of a benchmark run.
+#[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct BenchResult {
+    // The number of requests that were sent.
+    pub requests: usize,
+
+    // The number of requests that never got a response.
+    pub errors: usize,
+
+    // The number of concurrent requests.
+    pub threads: usize,
+
+    // The HTTP version the server answered with, like "HTTP/1.1" or "HTTP/2.0".
+    pub http_version: String,
+
+    // How long the scan took, in seconds.
+    pub elapsed_secs: f64,
+
+    // The number of answered requests per second.
+    pub requests_per_sec: f64,
+}
+
+/// Starts an HTTP server on a free loopback port that answers every request with a 200 response.
+/// The server runs in the background until the program exits. Returns the address it listens on.
+pub fn start_server() -> Result<SocketAddr, String> {
+    let make_service = make_service_fn(|_connection| async {
+        Ok::<_, Infallible>(service_fn(|_request| async {
+            Ok::<_, Infallible>(Response::new(Body::from(BENCH_BODY)))
+        }))
+    });
+
+    let server = Server::try_bind(&SocketAddr::from(([127, 0, 0, 1], 0)))
+        .map_err(|err| format!("Could not start the benchmark server: {}", err))?
+        .serve(make_service);
+    let address = server.local_addr();
+    tokio::spawn(server);
+    Ok(address)
+}
+
+/// Sends `requests` requests to a local benchmark server through the scan engine, using the
+/// client settings and thread count from `config`. Returns an error if the server can't be
+/// started or the client can't be built.
+pub async fn run_benchmark(config: &ScanConfig, requests: usize) -> Result<BenchResult, String> {
+    let address = start_server()?;
+    let client = config.build_client()?;
+    let site = format!("http://{}/", address);
+
+    // Send one request before the clock starts, so the connection is already open and the HTTP
+    // version the server agreed to is known.
+    let http_version = client
+        .get(&site)
+        .send()
+        .await
+        .map(|response| format!("{:?}", response.version()))
+        .map_err(|err| format!("Could not reach the benchmark server: {}", err))?;
+
+    let mut scanner = Scanner::new();
+    scanner.site = Arc::new(site);
+    scanner.retry_policy = config.retry_policy();
+    scanner.wordlist = Wordlist((0..requests).map(|n| format!("bench-{}", n)).collect());
+    let probes = scanner.dir_probes();
+
+    let threads = config.thread_count();
+    let started = Instant::now();
+    scanner
+        .run(&client, probes, threads, &ProgressBar::hidden())
+        .await;
+    let elapsed_secs = started.elapsed().as_secs_f64();
+
+    let errors = scanner.unreachable.len();
+    Ok(BenchResult {
+        requests,
+        errors,
+        threads,
+        http_version,
+        elapsed_secs,
+        requests_per_sec: (requests - errors) as f64 / elapsed_secs.max(f64::EPSILON),
+    })
+}
+
+impl BenchResult {
+    /// Prints the benchmark results to the terminal.
+    pub fn display(&self) {
+        println!("---------------------------------------------------------");
+        println!("Benchmark Results");
+        println!("Protocol: {}", self.http_version);
+        println!("Threads: {}", self.threads);
+        println!("---------------------------------------------------------");
+        println!(
+            "{} requests in {:.2} seconds",
+            self.requests, self.elapsed_secs
+        );
+        let rate = format!("{:.0} requests/sec", self.requests_per_sec).green();
+        println!("{}", rate);
+        if self.errors > 0 {
+            let errors = format!("{} requests never got a response", self.errors).red();
+            println!("{}", errors);
+        }
+    }
+}
@@ -0,0 +1,424 @@
+//! Retrying forbidden pages with common access control bypasses.
+//!
+//! A page that answers 401 or 403 is often only protected by a rule in front of the application,
+//! like a reverse proxy that matches the exact path or trusts a header. After the scan, every
+//! result with one of those status codes can be requested again with a few variations: the path in
+//! an `X-Original-URL` header, an `X-Forwarded-For` header claiming the request came from the
+//! server itself, a `/%2e` in front of the path, a trailing `/.`, the path in another case, and
+//! other methods. A variant is reported when it gets a success or a redirect that the scan would
+//! keep. The `X-Original-URL` variant requests `/`, so it is only reported when it gets another
+//! status code than `/` without the header, and the POST variant is only sent when dangerous
+//! requests are allowed.
+//!
+//! The variants are sent as raw HTTP/1.1 requests instead of through reqwest, since its url
+//! parser removes dot segments like `/%2e` and `/.` before the request is sent.
+
+use crate::audit::{now_ms, AuditEntry, AuditLog};
+use crate::openapi::SAFE_METHODS;
+use crate::results::{ResultKey, ScanResult};
+use crate::{result_line, Scanner};
+use futures::stream::{self, StreamExt};
+use indicatif::ProgressBar;
+use reqwest::header::{HeaderMap, HeaderName, HeaderValue, CONNECTION, CONTENT_LENGTH, HOST};
+use reqwest::Url;
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+use std::time::Duration;
+use tokio::io::{AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt};
+use tokio::net::TcpStream;
+use tokio_native_tls::{native_tls, TlsConnector};
+
+/// The status codes of the results bypasses are tried for.
+pub const BYPASS_STATUSES: [u16; 2] = [401, 403];
+
+/// The name of the variant that sends the path in an `X-Original-URL` header on a request for `/`.
+pub const ORIGINAL_URL_VARIANT: &str = "X-Original-URL";
+
+/// The most bytes of a response that are read while looking for the end of its headers.
+const MAX_HEAD_BYTES: usize = 64 * 1024;
+
+/// A variation of a request that might get past an access control rule.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct BypassVariant {
+    // What was changed, like "X-Original-URL" or "trailing /.".
+    pub name: String,
+
+    pub method: String,
+
+    // The request target sent on the request line, like "/%2e/admin".
+    pub target: String,
+
+    // Headers sent in addition to the configured ones, replacing those with the same name.
+    pub headers: Vec<(String, String)>,
+}
+
+impl BypassVariant {
+    fn new(name: &str, method: &str, target: String) -> Self {
+        BypassVariant {
+            name: name.to_string(),
+            method: method.to_string(),
+            target,
+            headers: Vec::new(),
+        }
+    }
+
+    fn with_header(mut self, name: &str, value: &str) -> Self {
+        self.headers.push((name.to_string(), value.to_string()));
+        self
+    }
+}
+
+/// Returns the variants tried for a page at `url` that was requested with `method`. Variants that
+/// would send the same request as the page, like the path in another case when it has no letters,
+/// are left out, and so are methods that can change data unless `send_unsafe` is true.
+pub fn bypass_variants(url: &Url, method: &str, send_unsafe: bool) -> Vec<BypassVariant> {
+    let path = url.path();
+    let query = match url.query() {
+        Some(query) => format!("?{}", query),
+        None => String::new(),
+    };
+    let target = format!("{}{}", path, query);
+
+    let mut variants = vec![
+        BypassVariant::new(ORIGINAL_URL_VARIANT, method, format!("/{}", query))
+            .with_header("X-Original-URL", path),
+        BypassVariant::new("X-Forwarded-For", method, target.clone())
+            .with_header("X-Forwarded-For", "127.0.0.1"),
+        BypassVariant::new("/%2e prefix", method, format!("/%2e{}{}", path, query)),
+        BypassVariant::new(
+            "trailing /.",
+            method,
+            format!("{}/.{}", path.trim_end_matches('/'), query),
+        ),
+    ];
+
+    // Lowercase paths are tried in uppercase, anything else in lowercase.
+    let changed_case = match path == path.to_lowercase() {
+        true => path.to_uppercase(),
+        false => path.to_lowercase(),
+    };
+    if changed_case != path {
+        variants.push(BypassVariant::new(
+            "case",
+            method,
+            format!("{}{}", changed_case, query),
+        ));
+    }
+
+    for other in ["GET", "POST", "HEAD"] {
+        if other != method && (send_unsafe || SAFE_METHODS.contains(&other)) {
+            variants.push(BypassVariant::new(
+                &format!("{} method", other),
+                other,
+                target.clone(),
+            ));
+        }
+    }
+    variants
+}
+
+/// A variant of a forbidden page that got a different status code.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct BypassResult {
+    // The key of the forbidden result in `Scanner::found`.
+    pub original: ResultKey,
+
+    // The status code the forbidden result answered with.
+    pub original_status: u16,
+
+    pub variant: BypassVariant,
+
+    // The full url of the variant, with the request target as it was sent.
+    pub url: String,
+
+    // The HTTP status code the variant answered with.
+    pub status: u16,
+}
+
+impl BypassResult {
+    /// Returns how the bypass is shown in the terminal, like
+    /// "GET http://example.com/%2e/admin (/%2e prefix, was 403)".
+    pub fn display_name(&self) -> String {
+        format!(
+            "{} {} ({}, was {})",
+            self.variant.method, self.url, self.variant.name, self.original_status
+        )
+    }
+}
+
+/// Returns "scheme://host:port" of `url`, which the request target of a variant is appended to.
+fn origin(url: &Url) -> String {
+    url.origin().ascii_serialization()
+}
+
+/// Returns the value of the `Host` header for `url`: its host, and its port if it isn't the
+/// default one for the scheme.
+fn authority(url: &Url) -> String {
+    let host = url.host_str().unwrap_or_default();
+    match url.port() {
+        Some(port) => format!("{}:{}", host, port),
+        None => host.to_string(),
+    }
+}
+
+/// Builds the headers sent with `variant` of the page at `url`: the configured `headers`, the
+/// `Host` header the page was requested with, and the headers of the variant. Returns an error if
+/// a header of the variant can't be sent.
+fn variant_headers(
+    url: &Url,
+    host: Option<&str>,
+    variant: &BypassVariant,
+    headers: &HeaderMap,
+) -> Result<HeaderMap, String> {
+    let mut headers = headers.clone();
+    let value = |value: &str| {
+        HeaderValue::from_str(value).map_err(|_| format!("Invalid header: {}", value))
+    };
+    if let Some(host) = host {
+        headers.insert(HOST, value(host)?);
+    }
+    for (name, text) in &variant.headers {
+        let name = HeaderName::from_bytes(name.as_bytes())
+            .map_err(|_| format!("Invalid header name: {}", name))?;
+        headers.insert(name, value(text)?);
+    }
+    if !headers.contains_key(HOST) {
+        headers.insert(HOST, value(&authority(url))?);
+    }
+    if variant.method == "POST" {
+        headers.insert(CONTENT_LENGTH, HeaderValue::from_static("0"));
+    }
+    headers.insert(CONNECTION, HeaderValue::from_static("close"));
+    Ok(headers)
+}
+
+/// Sends the request line `method` `target` with `headers` to the host of `url` over a new
+/// connection, with TLS for https urls, and returns the status code of the response. Only the
+/// status line and headers of the response are read.
+async fn send_raw(
+    url: &Url,
+    method: &str,
+    target: &str,
+    headers: &HeaderMap,
+) -> Result<u16, String> {
+    let mut request = format!("{} {} HTTP/1.1\r\n", method, target).into_bytes();
+    for (name, value) in headers {
+        request.extend_from_slice(name.as_str().as_bytes());
+        request.extend_from_slice(b": ");
+        request.extend_from_slice(value.as_bytes());
+        request.extend_from_slice(b"\r\n");
+    }
+    request.extend_from_slice(b"\r\n");
+
+    let host = url.host_str().unwrap_or_default();
+    let port = url.port_or_known_default().unwrap_or(80);
+    let stream = TcpStream::connect(format!("{}:{}", host, port))
+        .await
+        .map_err(|err| format!("could not connect: {}", err))?;
+    match url.scheme() {
+        "https" => {
+            let connector = native_tls::TlsConnector::new()
+                .map(TlsConnector::from)
+                .map_err(|err| format!("could not set up TLS: {}", err))?;
+            // IPv6 hosts are written in brackets in urls, but not in certificates.
+            let domain = host.trim_start_matches('[').trim_end_matches(']');
+            let stream = connector
+                .connect(domain, stream)
+                .await
+                .map_err(|err| format!("could not set up TLS: {}", err))?;
+            exchange(stream, &request).await
+        }
+        _ => exchange(stream, &request).await,
+    }
+}
+
+/// Writes `request` to `stream` and reads the status code from the response.
+async fn exchange<S: AsyncRead + AsyncWrite + Unpin>(
+    mut stream: S,
+    request: &[u8],
+) -> Result<u16, String> {
+    stream
+        .write_all(request)
+        .await
+        .map_err(|err| format!("could not send the request: {}", err))?;
+
+    let mut head = Vec::new();
+    let mut chunk = [0u8; 1024];
+    while !head.windows(4).any(|window| window == b"\r\n\r\n") && head.len() < MAX_HEAD_BYTES {
+        match stream.read(&mut chunk).await {
+            Ok(0) => break,
+            Ok(read) => head.extend_from_slice(&chunk[..read]),
+            Err(err) => return Err(format!("could not read the response: {}", err)),
+        }
+    }
+    String::from_utf8_lossy(&head)
+        .lines()
+        .next()
+        .and_then(|line| line.split_whitespace().nth(1))
+        .and_then(|status| status.parse::<u16>().ok())
+        .ok_or_else(|| "the server didn't answer with HTTP".to_string())
+}
+
+/// Sends `variant` of the forbidden `result` within `timeout`, and writes it to the `audit` log if
+/// there is one. Returns the status code of the response.
+async fn try_variant(
+    result: &ScanResult,
+    variant: &BypassVariant,
+    headers: &HeaderMap,
+    timeout: Duration,
+    audit: Option<&AuditLog>,
+) -> Result<u16, String> {
+    let url = Url::parse(&result.url).map_err(|err| err.to_string())?;
+    let headers = variant_headers(&url, result.host.as_deref(), variant, headers)?;
+    let timestamp_ms = now_ms();
+    let status = match tokio::time::timeout(
+        timeout,
+        send_raw(&url, &variant.method, &variant.target, &headers),
+    )
+    .await
+    {
+        Ok(status) => status,
+        Err(_) => Err("the request timed out".to_string()),
+    };
+    if let Some(audit) = audit {
+        audit.record(&AuditEntry {
+            timestamp_ms,
+            method: variant.method.clone(),
+            url: format!("{}{}", origin(&url), variant.target),
+            headers_hash: audit.headers_hash(&headers),
+            attempt: 1,
+            status: status.as_ref().ok().copied(),
+            bytes: None,
+            error: status.as_ref().err().cloned(),
+        });
+    }
+    status
+}
+
+/// Returns true if `status` is a success or a redirect, the only answers reported as bypasses.
+/// Errors like a 400 for a malformed path or a 405 for another method don't get past anything.
+pub fn is_bypass_status(status: u16) -> bool {
+    (200..400).contains(&status)
+}
+
+impl Scanner {
+    /// Tries every bypass variant on the results that answered 401 or 403, sending `thread_count`
+    /// at a time with the configured `headers`, and waiting `timeout` for each. Methods that can
+    /// change data are only tried when `send_unsafe` is true. Variants are checked against the
+    /// scope like any other request.
+    ///
+    /// A variant that gets a success or a redirect the scan would keep is stored in `bypasses`,
+    /// printed above the `progress_bar`, and tagged on its result in `found`. The `X-Original-URL`
+    /// variant requests `/`, so `/` is requested without the header too, and the variant only
+    /// counts if it got another status code. Returns the number of bypasses.
+    pub async fn probe_bypasses(
+        &mut self,
+        headers: &HeaderMap,
+        timeout: Duration,
+        thread_count: usize,
+        send_unsafe: bool,
+        progress_bar: &ProgressBar,
+    ) -> usize {
+        let forbidden: Vec<ScanResult> = self
+            .found
+            .iter()
+            .filter(|result| BYPASS_STATUSES.contains(&result.status))
+            .cloned()
+            .collect();
+
+        // Each attempt is marked with whether it is the request a variant is compared with.
+        let mut attempts = Vec::new();
+        let mut compared = HashSet::new();
+        for result in forbidden {
+            let url = match Url::parse(&result.url) {
+                Ok(url) => url,
+                Err(_) => continue,
+            };
+            for variant in bypass_variants(&url, &result.method, send_unsafe) {
+                let variant_url = format!("{}{}", origin(&url), variant.target);
+                if self.check_scope(&variant_url).is_err() {
+                    continue;
+                }
+                let key = (
+                    variant_url.clone(),
+                    result.host.clone(),
+                    variant.method.clone(),
+                );
+                if variant.name == ORIGINAL_URL_VARIANT
+                    && !compared.contains(&key)
+                    && self.check_scope(&variant_url).is_ok()
+                {
+                    compared.insert(key);
+                    let plain = BypassVariant::new(
+                        "without headers",
+                        &variant.method,
+                        variant.target.clone(),
+                    );
+                    attempts.push((result.clone(), plain, variant_url.clone(), true));
+                }
+                attempts.push((result.clone(), variant, variant_url, false));
+            }
+        }
+        progress_bar.set_length(attempts.len() as u64);
+
+        let audit = self.audit.clone();
+        let answers: Vec<_> = stream::iter(attempts)
+            .map(|(result, variant, url, plain)| {
+                let audit = audit.as_deref();
+                async move {
+                    let status = try_variant(&result, &variant, headers, timeout, audit).await;
+                    progress_bar.inc(1);
+                    (result, variant, url, plain, status)
+                }
+            })
+            .buffered(thread_count)
+            .collect()
+            .await;
+
+        // The status codes of the pages the header variants were sent to, without the headers.
+        let plain_statuses: HashMap<_, u16> = answers
+            .iter()
+            .filter(|(_, _, _, plain, _)| *plain)
+            .filter_map(|(result, variant, url, _, status)| {
+                let key = (url.clone(), result.host.clone(), variant.method.clone());
+                Some((key, *status.as_ref().ok()?))
+            })
+            .collect();
+
+        let mut bypasses = 0;
+        for (result, variant, url, plain, status) in answers {
+            let key = (url.clone(), result.host.clone(), variant.method.clone());
+            let status = match status {
+                Ok(status)
+                    if !plain
+                        && status != result.status
+                        && is_bypass_status(status)
+                        && !self.should_ignore(&status)
+                        && plain_statuses.get(&key) != Some(&status) =>
+                {
+                    status
+                }
+                _ => continue,
+            };
+            let bypass = BypassResult {
+                original: result.key(),
+                original_status: result.status,
+                variant,
+                url,
+                status,
+            };
+            progress_bar.println(result_line(&bypass.display_name(), status).to_string());
+
+            if let Some(original) = self.found.get(&bypass.original) {
+                let mut tagged = original.clone();
+                tagged
+                    .tags
+                    .push(format!("BYPASS {}: {}", bypass.variant.name, status));
+                self.found.insert(tagged);
+            }
+            self.bypasses.push(bypass);
+            bypasses += 1;
+        }
+        bypasses
+    }
+}
@@ -0,0 +1,630 @@
+//! Typed scan configuration and TOML config files.
+//!
+//! A `ScanConfig` holds every setting a scan can be run with. Settings can come from the
+//! `[defaults]` table of a config file, from a named `[profiles.<name>]` table in the same file,
+//! and from the command line. Each layer overrides the values set by the one before it.
+//!
+//! ```toml
+//! [defaults]
+//! wordlists = ["/usr/share/wordlists/dirb/common.txt"]
+//! ignore = ["403", "5xx"]
+//! threads = 8
+//! json = "results.json"
+//! max_attempts = 4
+//! timeout = 10.0
+//!
+//! [defaults.headers]
+//! User-Agent = "rustenum"
+//!
+//! [profiles.php]
+//! extensions = ["php", "phtml"]
+//! ```
+
+use crate::analysis::SignatureSet;
+use crate::audit::AuditLog;
+use crate::params::ParamMethod;
+use crate::retry::RetryPolicy;
+use crate::scope::{PathPattern, Scope};
+use crate::services::{parse_services, LiveService, Service};
+use crate::StatusMatcher;
+use reqwest::header::{HeaderMap, HeaderName, HeaderValue};
+use reqwest::Client;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fs;
+use std::time::Duration;
+
+/// The number of concurrent requests used when none is configured.
+pub const DEFAULT_THREADS: usize = 10;
+
+/// The highest number of concurrent requests allowed. This is capped to prevent DoS
+/// (malicious or accidental).
+pub const MAX_THREADS: usize = 14;
+
+/// The HTTP version requests are sent with.
+#[derive(Default, Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum HttpVersion {
+    // HTTP/1.1, or whatever version the client and server agree on.
+    #[default]
+    Auto,
+
+    // Only HTTP/1.1. Connections are reused, but carry one request at a time.
+    Http1,
+
+    // HTTP/2 with prior knowledge, without asking the server first. This also works for plain
+    // `http://` urls (h2c), where every request is multiplexed over a few connections.
+    Http2,
+}
+
+impl HttpVersion {
+    /// Parses the name of an HTTP version as it is written in config files and on the command line.
+    pub fn parse(name: &str) -> Result<Self, String> {
+        match name.to_lowercase().as_str() {
+            "auto" => Ok(HttpVersion::Auto),
+            "http1" | "1" | "1.1" => Ok(HttpVersion::Http1),
+            "http2" | "2" | "h2c" => Ok(HttpVersion::Http2),
+            _ => Err("the HTTP version needs to be one of auto, http1 or http2".to_string()),
+        }
+    }
+}
+
+/// The settings for a single scan. Every field is optional so that partial configurations
+/// can be layered on top of each other with `merge`.
+#[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ScanConfig {
+    // The base url to enumerate.
+    pub url: Option<String>,
+
+    // Paths to the wordlist files whose words are requested.
+    pub wordlists: Vec<String>,
+
+    // File extensions to append to each word.
+    pub extensions: Vec<String>,
+
+    // HTTP status codes to ignore in addition to 404, as codes, ranges like "400-499", classes
+    // like "4xx", and negated rules like "!403".
+    pub ignore: StatusMatcher,
+
+    // When this has rules, only HTTP status codes matched by it are kept.
+    pub match_status: StatusMatcher,
+
+    // Extra headers sent with every request, by header name.
+    pub headers: BTreeMap<String, String>,
+
+    // The number of concurrent requests.
+    pub threads: Option<usize>,
+
+    // Path to write the plain text results to.
+    pub output: Option<String>,
+
+    // Path to write the JSON results to.
+    pub json: Option<String>,
+
+    // Path to write the HTML report to.
+    pub html: Option<String>,
+
+    // How many times slower than the median response a page has to be to be flagged as slow.
+    pub slow_factor: Option<f64>,
+
+    // The total number of times a request is sent before its word is given up on.
+    pub max_attempts: Option<u32>,
+
+    // Milliseconds to wait before the first retry. The wait doubles after every attempt.
+    pub backoff_ms: Option<u64>,
+
+    // HTTP status codes that are retried. 502, 503 and 504 are retried when this is empty.
+    pub retry_statuses: Vec<u16>,
+
+    // Seconds to wait for a connection to the server to be made.
+    pub connect_timeout: Option<f64>,
+
+    // Seconds to wait for a whole response, from sending the request until the body is read.
+    pub timeout: Option<f64>,
+
+    // Path to write the words that never got a response to, so they can be scanned again.
+    pub unreachable: Option<String>,
+
+    // The HTTP version requests are sent with.
+    pub http_version: Option<HttpVersion>,
+
+    // The most idle connections kept open per host for reuse. 0 opens a new connection for
+    // every request.
+    pub pool_size: Option<usize>,
+
+    // How the candidate parameters of a `params` scan are sent.
+    pub param_method: Option<ParamMethod>,
+
+    // The number of candidate parameters sent in each request of a `params` scan.
+    pub batch_size: Option<usize>,
+
+    // Check every response body against the built-in signatures.
+    pub analyze: Option<bool>,
+
+    // Path to a signatures file to check every response body against. Setting this turns on
+    // body analysis with these signatures instead of the built-in ones.
+    pub signatures: Option<String>,
+
+    // Directory to save the raw response of every matched result to.
+    pub save_responses: Option<String>,
+
+    // Show the interactive dashboard instead of the progress bar.
+    pub dashboard: Option<bool>,
+
+    // List every result again, sorted by status code, once the scan is done.
+    pub list_results: Option<bool>,
+
+    // Only paths matching one of these patterns are requested. Patterns are globs, or regular
+    // expressions when they start with "re:".
+    pub include: Vec<String>,
+
+    // Paths matching any of these patterns are never requested.
+    pub exclude: Vec<String>,
+
+    // Path segments that are never requested. `DEFAULT_DANGEROUS` is used when this is empty.
+    pub dangerous: Vec<String>,
+
+    // Request the dangerous path segments too.
+    pub allow_dangerous: Option<bool>,
+
+    // The most requests a scan may send.
+    pub max_requests: Option<usize>,
+
+    // Path to write the candidate requests that were out of scope to.
+    pub skipped: Option<String>,
+
+    // Path of the audit log every request is appended to.
+    pub audit_log: Option<String>,
+
+    // Probe the host of the url on several schemes and ports, and scan every service that
+    // answers.
+    pub probe_services: Option<bool>,
+
+    // The services to probe, like "https:8443", or a port on its own for both http and https.
+    // `DEFAULT_SERVICES` are probed when this is empty. Setting this turns on probing.
+    pub services: Vec<String>,
+
+    // Path to a file of urls to scan, one per line, along with the url.
+    pub targets: Option<String>,
+
+    // The addresses of the workers the scan is shared between, like "10.0.0.5:7878". The scan
+    // sends its requests itself when this is empty.
+    pub workers: Vec<String>,
+
+    // The number of requests in each shard handed to a worker.
+    pub shard_size: Option<usize>,
+
+    // Commands of external programs every response is given to, as plugins.
+    pub plugins: Vec<String>,
+
+    // Retry every result that answered 401 or 403 with common access control bypasses.
+    pub bypass: Option<bool>,
+
+    // Look for an API description at the common paths, and request every documented operation.
+    pub openapi: Option<bool>,
+
+    // Urls or files of API descriptions whose operations are requested. Setting this turns on
+    // API enumeration without looking for a description.
+    pub openapi_specs: Vec<String>,
+
+    // Check the results at common GraphQL paths for GraphQL endpoints with introspection enabled.
+    pub graphql: Option<bool>,
+
+    // The directory the schemas of GraphQL endpoints are saved to. `DEFAULT_SCHEMA_DIR` is used
+    // when this isn't set. Setting this turns on the GraphQL checks.
+    pub graphql_schemas: Option<String>,
+}
+
+impl ScanConfig {
+    /// Make a new empty configuration.
+    pub fn new() -> Self {
+        ScanConfig::default()
+    }
+
+    /// Returns this configuration with every value set in `overrides` replacing its own. Lists are
+    /// replaced as a whole when `overrides` has any values in them. Headers are merged by name.
+    pub fn merge(mut self, overrides: ScanConfig) -> Self {
+        if overrides.url.is_some() {
+            self.url = overrides.url;
+        }
+        if !overrides.wordlists.is_empty() {
+            self.wordlists = overrides.wordlists;
+        }
+        if !overrides.extensions.is_empty() {
+            self.extensions = overrides.extensions;
+        }
+        if !overrides.ignore.is_empty() {
+            self.ignore = overrides.ignore;
+        }
+        if !overrides.match_status.is_empty() {
+            self.match_status = overrides.match_status;
+        }
+        self.headers.extend(overrides.headers);
+        if overrides.threads.is_some() {
+            self.threads = overrides.threads;
+        }
+        if overrides.output.is_some() {
+            self.output = overrides.output;
+        }
+        if overrides.json.is_some() {
+            self.json = overrides.json;
+        }
+        if overrides.html.is_some() {
+            self.html = overrides.html;
+        }
+        if overrides.slow_factor.is_some() {
+            self.slow_factor = overrides.slow_factor;
+        }
+        if overrides.max_attempts.is_some() {
+            self.max_attempts = overrides.max_attempts;
+        }
+        if overrides.backoff_ms.is_some() {
+            self.backoff_ms = overrides.backoff_ms;
+        }
+        if !overrides.retry_statuses.is_empty() {
+            self.retry_statuses = overrides.retry_statuses;
+        }
+        if overrides.connect_timeout.is_some() {
+            self.connect_timeout = overrides.connect_timeout;
+        }
+        if overrides.timeout.is_some() {
+            self.timeout = overrides.timeout;
+        }
+        if overrides.unreachable.is_some() {
+            self.unreachable = overrides.unreachable;
+        }
+        if overrides.http_version.is_some() {
+            self.http_version = overrides.http_version;
+        }
+        if overrides.pool_size.is_some() {
+            self.pool_size = overrides.pool_size;
+        }
+        if overrides.param_method.is_some() {
+            self.param_method = overrides.param_method;
+        }
+        if overrides.batch_size.is_some() {
+            self.batch_size = overrides.batch_size;
+        }
+        if overrides.analyze.is_some() {
+            self.analyze = overrides.analyze;
+        }
+        if overrides.signatures.is_some() {
+            self.signatures = overrides.signatures;
+        }
+        if overrides.save_responses.is_some() {
+            self.save_responses = overrides.save_responses;
+        }
+        if overrides.dashboard.is_some() {
+            self.dashboard = overrides.dashboard;
+        }
+        if overrides.list_results.is_some() {
+            self.list_results = overrides.list_results;
+        }
+        if !overrides.include.is_empty() {
+            self.include = overrides.include;
+        }
+        if !overrides.exclude.is_empty() {
+            self.exclude = overrides.exclude;
+        }
+        if !overrides.dangerous.is_empty() {
+            self.dangerous = overrides.dangerous;
+        }
+        if overrides.allow_dangerous.is_some() {
+            self.allow_dangerous = overrides.allow_dangerous;
+        }
+        if overrides.max_requests.is_some() {
+            self.max_requests = overrides.max_requests;
+        }
+        if overrides.skipped.is_some() {
+            self.skipped = overrides.skipped;
+        }
+        if overrides.audit_log.is_some() {
+            self.audit_log = overrides.audit_log;
+        }
+        if overrides.probe_services.is_some() {
+            self.probe_services = overrides.probe_services;
+        }
+        if !overrides.services.is_empty() {
+            self.services = overrides.services;
+        }
+        if overrides.targets.is_some() {
+            self.targets = overrides.targets;
+        }
+        if !overrides.workers.is_empty() {
+            self.workers = overrides.workers;
+        }
+        if overrides.shard_size.is_some() {
+            self.shard_size = overrides.shard_size;
+        }
+        if !overrides.plugins.is_empty() {
+            self.plugins = overrides.plugins;
+        }
+        if overrides.bypass.is_some() {
+            self.bypass = overrides.bypass;
+        }
+        if overrides.openapi.is_some() {
+            self.openapi = overrides.openapi;
+        }
+        if !overrides.openapi_specs.is_empty() {
+            self.openapi_specs = overrides.openapi_specs;
+        }
+        if overrides.graphql.is_some() {
+            self.graphql = overrides.graphql;
+        }
+        if overrides.graphql_schemas.is_some() {
+            self.graphql_schemas = overrides.graphql_schemas;
+        }
+        self
+    }
+
+    /// Returns the configured number of concurrent requests, or the default if none is set.
+    pub fn thread_count(&self) -> usize {
+        self.threads.unwrap_or(DEFAULT_THREADS)
+    }
+
+    /// Converts the configured headers into a `HeaderMap` that can be given to a reqwest client.
+    /// Returns an error if a header name or value isn't valid in an HTTP request.
+    pub fn header_map(&self) -> Result<HeaderMap, String> {
+        let mut header_map = HeaderMap::new();
+        for (name, value) in &self.headers {
+            let header_name = HeaderName::from_bytes(name.as_bytes())
+                .map_err(|_| format!("Invalid header name: {}", name))?;
+            let header_value = HeaderValue::from_str(value)
+                .map_err(|_| format!("Invalid value for header {}: {}", name, value))?;
+            header_map.insert(header_name, header_value);
+        }
+        Ok(header_map)
+    }
+
+    /// Returns the retry policy described by the configuration. Settings that aren't configured
+    /// use the defaults from `RetryPolicy::default`.
+    pub fn retry_policy(&self) -> RetryPolicy {
+        let mut policy = RetryPolicy::default();
+        if let Some(max_attempts) = self.max_attempts {
+            policy.max_attempts = max_attempts;
+        }
+        if let Some(backoff_ms) = self.backoff_ms {
+            policy.backoff_ms = backoff_ms;
+        }
+        if !self.retry_statuses.is_empty() {
+            policy.retry_statuses = self.retry_statuses.clone();
+        }
+        policy
+    }
+
+    /// Returns the signatures response bodies are checked against, or None if bodies aren't
+    /// analyzed. Returns an error if the signatures file can't be read or parsed.
+    pub fn signature_set(&self) -> Result<Option<SignatureSet>, String> {
+        match &self.signatures {
+            Some(path) => SignatureSet::from_file(path).map(Some),
+            None if self.analyze == Some(true) => Ok(Some(SignatureSet::builtin())),
+            None => Ok(None),
+        }
+    }
+
+    /// Opens the audit log, or returns None if there isn't one. Returns an error if the file can't
+    /// be opened or a header is invalid.
+    pub fn audit_log(&self) -> Result<Option<AuditLog>, String> {
+        match &self.audit_log {
+            Some(path) => AuditLog::open(path, self.header_map()?).map(Some),
+            None => Ok(None),
+        }
+    }
+
+    /// Returns the scope described by the configuration. Dangerous paths are skipped unless they
+    /// are allowed. Returns an error if an include or exclude pattern is invalid.
+    pub fn scope(&self) -> Result<Scope, String> {
+        let mut scope = Scope::default();
+        for pattern in &self.include {
+            scope.include.push(PathPattern::new(pattern)?);
+        }
+        for pattern in &self.exclude {
+            scope.exclude.push(PathPattern::new(pattern)?);
+        }
+        if !self.dangerous.is_empty() {
+            scope.dangerous = self.dangerous.clone();
+        }
+        if self.allow_dangerous == Some(true) {
+            scope.dangerous.clear();
+        }
+        scope.max_requests = self.max_requests;
+        Ok(scope)
+    }
+
+    /// Builds the reqwest client every request of a scan is sent with. The client never follows
+    /// redirects, sends the configured headers, and uses the configured timeouts, HTTP version and
+    /// connection pool size. Returns an error if a header is invalid or the client can't be built.
+    pub fn build_client(&self) -> Result<Client, String> {
+        let mut builder = Client::builder()
+            // Set the client to never follow redirects.
+            .redirect(reqwest::redirect::Policy::none())
+            // Send the configured headers with every request.
+            .default_headers(self.header_map()?);
+        if let Some(connect_timeout) = self.connect_timeout {
+            builder = builder.connect_timeout(Duration::from_secs_f64(connect_timeout));
+        }
+        if let Some(timeout) = self.timeout {
+            builder = builder.timeout(Duration::from_secs_f64(timeout));
+        }
+        match self.http_version.unwrap_or_default() {
+            HttpVersion::Auto => {}
+            HttpVersion::Http1 => builder = builder.http1_only(),
+            HttpVersion::Http2 => builder = builder.http2_prior_knowledge(),
+        }
+        if let Some(pool_size) = self.pool_size {
+            builder = builder.pool_max_idle_per_host(pool_size);
+        }
+        builder
+            .build()
+            .map_err(|err| format!("Could not build the HTTP client: {}", err))
+    }
+
+    /// Returns true if the host of the url is probed for services instead of being scanned
+    /// at the url as it is.
+    pub fn probes_services(&self) -> bool {
+        self.probe_services == Some(true) || !self.services.is_empty()
+    }
+
+    /// Returns true if the operations of an API description are requested along with the
+    /// wordlist.
+    pub fn uses_openapi(&self) -> bool {
+        self.openapi == Some(true) || !self.openapi_specs.is_empty()
+    }
+
+    /// Returns true if the results are checked for GraphQL endpoints once the scan is done.
+    pub fn uses_graphql(&self) -> bool {
+        self.graphql == Some(true) || self.graphql_schemas.is_some()
+    }
+
+    /// Returns the services to probe the host on. Returns an error if one can't be parsed.
+    pub fn service_list(&self) -> Result<Vec<Service>, String> {
+        parse_services(&self.services)
+    }
+
+    /// Returns the configuration for scanning one `service` that was found by probing. The url is
+    /// replaced by the url of the service, and every output file and directory gets the service
+    /// added to its name, so the results of each service are kept apart. The audit log is shared.
+    pub fn for_service(&self, service: &LiveService) -> ScanConfig {
+        let path =
+            |path: &Option<String>| path.as_ref().map(|path| service.service.output_path(path));
+        ScanConfig {
+            url: Some(service.url.clone()),
+            output: path(&self.output),
+            json: path(&self.json),
+            html: path(&self.html),
+            unreachable: path(&self.unreachable),
+            save_responses: path(&self.save_responses),
+            skipped: path(&self.skipped),
+            probe_services: None,
+            services: Vec::new(),
+            ..self.clone()
+        }
+    }
+
+    /// Returns the urls of a scan of several targets: the url, if there is one, followed by every
+    /// url in the targets file, one per line. Blank lines, lines starting with '#' and repeats are
+    /// left out. Returns an error if the targets file can't be read.
+    pub fn target_urls(&self) -> Result<Vec<String>, String> {
+        let mut urls: Vec<String> = self.url.iter().cloned().collect();
+        if let Some(path) = &self.targets {
+            let contents = fs::read_to_string(path)
+                .map_err(|err| format!("Could not read the targets file {}: {}", path, err))?;
+            for line in contents.lines().map(str::trim) {
+                if !line.is_empty() && !line.starts_with('#') && !urls.iter().any(|url| url == line)
+                {
+                    urls.push(line.to_string());
+                }
+            }
+        }
+        Ok(urls)
+    }
+
+    /// Returns the settings a worker scans a shard with. The coordinator has already built every
+    /// request and checked it against the scope, and writes every output file itself, so the
+    /// worker only gets the settings for sending requests and keeping their results. No paths are
+    /// handed over: the signatures are sent already parsed with each job instead.
+    pub fn for_worker(&self) -> ScanConfig {
+        ScanConfig {
+            ignore: self.ignore.clone(),
+            match_status: self.match_status.clone(),
+            headers: self.headers.clone(),
+            threads: self.threads,
+            max_attempts: self.max_attempts,
+            backoff_ms: self.backoff_ms,
+            retry_statuses: self.retry_statuses.clone(),
+            connect_timeout: self.connect_timeout,
+            timeout: self.timeout,
+            http_version: self.http_version,
+            pool_size: self.pool_size,
+            allow_dangerous: Some(true),
+            ..Default::default()
+        }
+    }
+
+    /// Checks that the configuration can be used to run a scan. Returns a message describing
+    /// the first problem found.
+    pub fn validate(&self) -> Result<(), String> {
+        if self.url.is_none() && self.targets.is_none() {
+            return Err("No url to scan was provided. Please set `-u`/`--url`.".to_string());
+        }
+        self.validate_wordlists_and_threads()
+    }
+
+    /// Checks the settings needed by every kind of scan, including scans that have no url like
+    /// the `dns` scan. Returns a message describing the first problem found.
+    pub fn validate_wordlists_and_threads(&self) -> Result<(), String> {
+        if self.wordlists.is_empty() {
+            return Err("No wordlist was provided. Please set `-w`/`--wordlist`.".to_string());
+        }
+        let threads = self.thread_count();
+        if !(1..=MAX_THREADS).contains(&threads) {
+            return Err(format!(
+                "The thread count needs to be in the range of 1 to {} inclusive.",
+                MAX_THREADS
+            ));
+        }
+        if let Some(slow_factor) = self.slow_factor {
+            if slow_factor.is_nan() || slow_factor < 1.0 {
+                return Err("The slow factor needs to be at least 1.".to_string());
+            }
+        }
+        if self.batch_size == Some(0) {
+            return Err("The batch size needs to be at least 1.".to_string());
+        }
+        if self.shard_size == Some(0) {
+            return Err("The shard size needs to be at least 1.".to_string());
+        }
+        if self.max_requests == Some(0) {
+            return Err("The request budget needs to be at least 1.".to_string());
+        }
+        self.service_list()?;
+        if self.max_attempts == Some(0) {
+            return Err("The number of attempts needs to be at least 1.".to_string());
+        }
+        for timeout in [self.connect_timeout, self.timeout].into_iter().flatten() {
+            if !timeout.is_finite() || timeout <= 0.0 {
+                return Err("Timeouts need to be a positive number of seconds.".to_string());
+            }
+        }
+        Ok(())
+    }
+}
+
+/// The contents of a TOML config file: a set of default settings and any number of named
+/// profiles that are applied on top of the defaults.
+#[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ConfigFile {
+    // Settings used by every scan run with this file.
+    pub defaults: ScanConfig,
+
+    // Named groups of settings that can be chosen with `-p`/`--profile`.
+    pub profiles: BTreeMap<String, ScanConfig>,
+}
+
+impl ConfigFile {
+    /// Parses a config file from a string of TOML.
+    pub fn parse(contents: &str) -> Result<Self, String> {
+        toml::from_str(contents).map_err(|err| format!("Could not parse config file: {}", err))
+    }
+
+    /// Reads and parses the config file at `path`.
+    pub fn from_file(path: &str) -> Result<Self, String> {
+        let contents = fs::read_to_string(path)
+            .map_err(|err| format!("Could not read config file {}: {}", path, err))?;
+        ConfigFile::parse(&contents)
+    }
+
+    /// Returns the default settings with the settings of the profile named `profile` applied on
+    /// top of them. Returns an error if the profile doesn't exist.
+    pub fn resolve(&self, profile: Option<&str>) -> Result<ScanConfig, String> {
+        match profile {
+            None => Ok(self.defaults.clone()),
+            Some(name) => match self.profiles.get(name) {
+                Some(settings) => Ok(self.defaults.clone().merge(settings.clone())),
+                None => Err(format!("No profile named `{}` in the config file.", name)),
+            },
+        }
+    }
+}
@@ -0,0 +1,665 @@
+//! An interactive terminal dashboard for long running scans.
+//!
+//! The dashboard is a front-end to `Scanner::run` that replaces the progress bar. It draws a live
+//! table of the hits, counters for every status class, a graph of the request rate and a panel
+//! of recent errors, and it reads single key presses so the scan can be paused and resumed, run
+//! with more or fewer concurrent requests, or told to ignore another status code while it runs.
+//!
+//! The scan and the dashboard share a `ScanControl`. The scan records every response in it and
+//! asks it for permission before sending each request, and the dashboard draws it and changes it
+//! when keys are pressed. The terminal is put in raw mode and on the alternate screen with
+//! crossterm, behind a guard that gives the terminal back when the dashboard stops, when it is
+//! dropped, when the program panics and when it exits through `error`.
+
+use crate::config::MAX_THREADS;
+use crate::StatusMatcher;
+use colored::*;
+use crossterm::event::{self, Event, KeyCode, KeyEventKind, KeyModifiers};
+use crossterm::{cursor, execute, terminal};
+use std::io::{self, IsTerminal, Write};
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex, Once};
+use std::time::{Duration, Instant};
+use tokio::sync::{Notify, Semaphore, SemaphorePermit};
+use tokio::task::JoinHandle;
+
+/// The number of seconds shown in the request rate graph.
+pub const RATE_SECONDS: usize = 60;
+
+/// The number of recent errors kept for the error panel.
+pub const MAX_ERRORS: usize = 5;
+
+/// How often the dashboard is redrawn.
+const REFRESH_INTERVAL: Duration = Duration::from_millis(250);
+
+/// How long the key reader waits for a key press before checking whether the dashboard stopped.
+const KEY_POLL_INTERVAL: Duration = Duration::from_millis(100);
+
+/// True while the terminal is taken over by a dashboard.
+static TERMINAL_TAKEN: AtomicBool = AtomicBool::new(false);
+
+/// The bars of the request rate graph, from lowest to highest.
+const GRAPH_BARS: [char; 8] = ['▁', '▂', '▃', '▄', '▅', '▆', '▇', '█'];
+
+/// The key bindings shown at the bottom of the dashboard.
+const HELP: &str = "p pause/resume   +/- threads   i ignore a status code   / filter   1-5 show/hide a status class   q quit";
+
+/// A hit shown in the results table.
+#[derive(Default, Debug, Clone, PartialEq)]
+pub struct DashboardRow {
+    // The name as it is displayed, like "/admin".
+    pub name: String,
+
+    pub status: u16,
+
+    // The size of the response body in bytes.
+    pub size: Option<u64>,
+}
+
+/// What typed keys are used for.
+#[derive(Default, Debug, Clone, PartialEq)]
+pub enum KeyMode {
+    // Every key is a command.
+    #[default]
+    Commands,
+
+    // Keys are typed into the filter until enter is pressed.
+    Filter,
+
+    // Keys are typed into a status code to ignore until enter is pressed.
+    Ignore(String),
+}
+
+/// Everything the dashboard shows that the scan updates as it runs.
+#[derive(Default, Debug, Clone)]
+pub struct DashboardState {
+    // The url being scanned.
+    pub site: String,
+
+    // Every hit, in the order it arrived.
+    pub rows: Vec<DashboardRow>,
+
+    // The number of responses in each status class, ignored responses included. Index 1 counts
+    // 1xx responses up to index 5 for 5xx responses, and index 0 counts anything else.
+    pub class_counts: [usize; 6],
+
+    // Status classes whose hits are hidden from the table, indexed like `class_counts`.
+    pub hidden_classes: [bool; 6],
+
+    // The number of requests that are done and the number there are in total.
+    pub completed: usize,
+    pub total: usize,
+
+    // The total number of errors, and the most recent of them.
+    pub error_count: usize,
+    pub errors: Vec<String>,
+
+    // Responses per second for each of the last `RATE_SECONDS` seconds, oldest first.
+    pub rate: Vec<usize>,
+
+    // Responses received since the last rate sample was taken, and when that was.
+    pub rate_counter: usize,
+    pub rate_sampled_at: Option<Instant>,
+
+    // Only hits whose name contains this are shown.
+    pub filter: String,
+
+    // The status codes being ignored, and the codes added with the dashboard that the scan
+    // hasn't applied yet.
+    pub ignore_list: StatusMatcher,
+    pub pending_ignores: Vec<u16>,
+
+    pub key_mode: KeyMode,
+}
+
+/// The state shared by a running scan and its dashboard.
+#[derive(Debug)]
+pub struct ScanControl {
+    paused: AtomicBool,
+    stopped: AtomicBool,
+    concurrency: AtomicUsize,
+
+    // One permit for every request that may be in flight at once.
+    permits: Semaphore,
+
+    // Permits that are taken out of use as soon as they are given back, because the concurrency
+    // was lowered while they were in use.
+    excess: AtomicUsize,
+
+    // Woken when the scan is resumed or stopped.
+    resumed: Notify,
+
+    state: Mutex<DashboardState>,
+}
+
+/// Permission to send one request. The request's place is given back when this is dropped.
+pub struct Permit<'a> {
+    control: &'a ScanControl,
+    permit: Option<SemaphorePermit<'a>>,
+}
+
+impl Drop for Permit<'_> {
+    fn drop(&mut self) {
+        let excess =
+            self.control
+                .excess
+                .fetch_update(Ordering::SeqCst, Ordering::SeqCst, |excess| {
+                    excess.checked_sub(1)
+                });
+        if let (Ok(_), Some(permit)) = (excess, self.permit.take()) {
+            permit.forget();
+        }
+    }
+}
+
+/// Returns the index of the status class of `status` in `DashboardState::class_counts`.
+pub fn status_class(status: u16) -> usize {
+    match status {
+        100..=599 => (status / 100) as usize,
+        _ => 0,
+    }
+}
+
+impl ScanControl {
+    /// Makes the shared state for a scan of `site` that sends `total` requests, `concurrency` at a
+    /// time, and ignores the codes in `ignore_list`.
+    pub fn new(site: &str, concurrency: usize, total: usize, ignore_list: &StatusMatcher) -> Self {
+        let concurrency = concurrency.clamp(1, MAX_THREADS);
+        ScanControl {
+            paused: AtomicBool::new(false),
+            stopped: AtomicBool::new(false),
+            concurrency: AtomicUsize::new(concurrency),
+            permits: Semaphore::new(concurrency),
+            excess: AtomicUsize::new(0),
+            resumed: Notify::new(),
+            state: Mutex::new(DashboardState {
+                site: site.to_string(),
+                total,
+                ignore_list: ignore_list.clone(),
+                ..Default::default()
+            }),
+        }
+    }
+
+    pub fn is_paused(&self) -> bool {
+        self.paused.load(Ordering::SeqCst)
+    }
+
+    pub fn set_paused(&self, paused: bool) {
+        self.paused.store(paused, Ordering::SeqCst);
+        if !paused {
+            self.resumed.notify_waiters();
+        }
+    }
+
+    /// Returns true once the scan has been told to stop early.
+    pub fn is_stopped(&self) -> bool {
+        self.stopped.load(Ordering::SeqCst)
+    }
+
+    /// Tells the scan to stop. Requests that are waiting to be sent are never sent.
+    pub fn stop(&self) {
+        self.stopped.store(true, Ordering::SeqCst);
+        self.permits.close();
+        self.resumed.notify_waiters();
+    }
+
+    /// Returns the number of requests that may be in flight at once.
+    pub fn concurrency(&self) -> usize {
+        self.concurrency.load(Ordering::SeqCst)
+    }
+
+    /// Changes the number of requests that may be in flight at once, kept between 1 and
+    /// `MAX_THREADS`. Requests already in flight are allowed to finish.
+    pub fn set_concurrency(&self, concurrency: usize) {
+        let concurrency = concurrency.clamp(1, MAX_THREADS);
+        let previous = self.concurrency.swap(concurrency, Ordering::SeqCst);
+        if concurrency > previous {
+            // Permits that were still going to be taken out of use are kept instead.
+            let mut added = concurrency - previous;
+            let kept = self
+                .excess
+                .fetch_update(Ordering::SeqCst, Ordering::SeqCst, |excess| {
+                    Some(excess.saturating_sub(added))
+                })
+                .unwrap_or(0)
+                .min(added);
+            added -= kept;
+            self.permits.add_permits(added);
+        } else {
+            // Permits that aren't in use are taken out straight away, the others once the
+            // requests holding them are done.
+            let removed = previous - concurrency;
+            let forgotten = self.permits.forget_permits(removed);
+            self.excess.fetch_add(removed - forgotten, Ordering::SeqCst);
+        }
+    }
+
+    /// Waits until a request may be sent: the scan isn't paused and fewer requests than the
+    /// concurrency are in flight. Returns None if the scan was stopped while waiting.
+    pub async fn acquire(&self) -> Option<Permit<'_>> {
+        loop {
+            // The waiter is registered before the check, so a resume in between isn't missed.
+            let resumed = self.resumed.notified();
+            tokio::pin!(resumed);
+            resumed.as_mut().enable();
+            if self.is_stopped() {
+                return None;
+            }
+            if self.is_paused() {
+                resumed.await;
+                continue;
+            }
+
+            // The semaphore is closed when the scan is stopped.
+            let permit = Permit {
+                control: self,
+                permit: Some(self.permits.acquire().await.ok()?),
+            };
+            // The scan may have been paused while this request was waiting for its turn.
+            if !self.is_paused() {
+                return Some(permit);
+            }
+        }
+    }
+
+    /// Returns a copy of everything the dashboard shows.
+    pub fn snapshot(&self) -> DashboardState {
+        self.state.lock().unwrap().clone()
+    }
+
+    /// Counts a finished request. `status` is None if it never got a response.
+    pub fn record_response(&self, status: Option<u16>) {
+        let mut state = self.state.lock().unwrap();
+        state.completed += 1;
+        if let Some(status) = status {
+            state.class_counts[status_class(status)] += 1;
+            state.rate_counter += 1;
+        }
+    }
+
+    /// Takes requests that won't be sent, like the ones out of scope, out of the total.
+    pub fn remove_from_total(&self, count: usize) {
+        let mut state = self.state.lock().unwrap();
+        state.total = state.total.saturating_sub(count);
+    }
+
+    /// Adds requests that are sent after the ones the scan started with, like the paths enqueued
+    /// by plugins, to the total.
+    pub fn add_to_total(&self, count: usize) {
+        let mut state = self.state.lock().unwrap();
+        state.total += count;
+    }
+
+    /// Adds a hit to the results table.
+    pub fn record_hit(&self, name: &str, status: u16, size: Option<u64>) {
+        self.state.lock().unwrap().rows.push(DashboardRow {
+            name: name.to_string(),
+            status,
+            size,
+        });
+    }
+
+    /// Adds an error to the error panel.
+    pub fn record_error(&self, error: String) {
+        let mut state = self.state.lock().unwrap();
+        state.error_count += 1;
+        state.errors.push(error);
+        if state.errors.len() > MAX_ERRORS {
+            state.errors.remove(0);
+        }
+    }
+
+    /// Returns true if responses with `status` are ignored.
+    pub fn is_ignored(&self, status: u16) -> bool {
+        self.state.lock().unwrap().ignore_list.matches(status)
+    }
+
+    /// Returns the status codes added to the ignore list since the last call, so the scan can
+    /// apply them to its own ignore list.
+    pub fn take_ignores(&self) -> Vec<u16> {
+        std::mem::take(&mut self.state.lock().unwrap().pending_ignores)
+    }
+
+    /// Takes a sample of the request rate if a second or more has passed since the last one.
+    pub fn sample_rate(&self, now: Instant) {
+        let mut state = self.state.lock().unwrap();
+        let sampled_at = *state.rate_sampled_at.get_or_insert(now);
+        if now.duration_since(sampled_at) >= Duration::from_secs(1) {
+            let count = std::mem::take(&mut state.rate_counter);
+            state.rate.push(count);
+            if state.rate.len() > RATE_SECONDS {
+                state.rate.remove(0);
+            }
+            state.rate_sampled_at = Some(now);
+        }
+    }
+
+    /// Handles a single key press.
+    pub fn handle_key(&self, key: u8) {
+        let mut state = self.state.lock().unwrap();
+        match state.key_mode.clone() {
+            KeyMode::Commands => match key {
+                b'p' | b' ' => self.set_paused(!self.is_paused()),
+                b'+' | b'=' => self.set_concurrency(self.concurrency() + 1),
+                b'-' => self.set_concurrency(self.concurrency().saturating_sub(1)),
+                b'i' => state.key_mode = KeyMode::Ignore(String::new()),
+                b'/' => state.key_mode = KeyMode::Filter,
+                b'1'..=b'5' => {
+                    let class = (key - b'0') as usize;
+                    state.hidden_classes[class] = !state.hidden_classes[class];
+                }
+                b'q' => self.stop(),
+                _ => {}
+            },
+            KeyMode::Filter => match key {
+                b'\r' | b'\n' => state.key_mode = KeyMode::Commands,
+                // Escape clears the filter.
+                27 => {
+                    state.filter.clear();
+                    state.key_mode = KeyMode::Commands;
+                }
+                8 | 127 => {
+                    state.filter.pop();
+                }
+                key if key.is_ascii_graphic() => state.filter.push(key as char),
+                _ => {}
+            },
+            KeyMode::Ignore(mut code) => match key {
+                b'\r' | b'\n' => {
+                    state.key_mode = KeyMode::Commands;
+                    match code.parse::<u16>() {
+                        Ok(status) if (100..=599).contains(&status) => {
+                            if !state.ignore_list.matches(status) {
+                                state.ignore_list.add_code(status);
+                                state.pending_ignores.push(status);
+                            }
+                        }
+                        _ => {
+                            drop(state);
+                            self.record_error(format!("{} isn't a valid status code", code));
+                        }
+                    }
+                }
+                27 => state.key_mode = KeyMode::Commands,
+                8 | 127 => {
+                    code.pop();
+                    state.key_mode = KeyMode::Ignore(code);
+                }
+                b'0'..=b'9' if code.len() < 3 => {
+                    code.push(key as char);
+                    state.key_mode = KeyMode::Ignore(code);
+                }
+                _ => {}
+            },
+        }
+    }
+
+    /// Draws the dashboard for a terminal `width` columns wide and `height` rows tall. The
+    /// results table shows as many of the newest hits as fit.
+    pub fn render(&self, width: usize, height: usize) -> String {
+        let state = self.snapshot();
+        let rule = "-".repeat(width.min(100));
+        let mut lines = Vec::new();
+
+        let status = match (self.is_stopped(), self.is_paused()) {
+            (true, _) => "STOPPED".red(),
+            (false, true) => "PAUSED".yellow(),
+            (false, false) => "RUNNING".green(),
+        };
+        lines.push(format!(
+            "RustEnum   {}   [{}]   threads: {}",
+            state.site,
+            status,
+            self.concurrency()
+        ));
+        let percent = match state.total {
+            0 => 100,
+            total => state.completed * 100 / total,
+        };
+        lines.push(format!(
+            "Requests: {}/{} ({}%)   rate: {} req/s   errors: {}",
+            state.completed,
+            state.total,
+            percent,
+            state.rate.last().copied().unwrap_or(0),
+            state.error_count
+        ));
+
+        let mut counters = Vec::new();
+        for class in 1..=5 {
+            let counter = format!("{}xx: {}", class, state.class_counts[class]);
+            counters.push(match state.hidden_classes[class] {
+                true => format!("({} hidden)", counter),
+                false => counter,
+            });
+        }
+        if state.class_counts[0] > 0 {
+            counters.push(format!("other: {}", state.class_counts[0]));
+        }
+        lines.push(counters.join("   "));
+        lines.push(format!("Rate: {}", rate_graph(&state.rate)));
+        lines.push(format!(
+            "Filter: {}   Ignoring: {}",
+            match state.filter.is_empty() {
+                true => "(none)",
+                false => state.filter.as_str(),
+            },
+            state.ignore_list
+        ));
+        lines.push(rule.clone());
+
+        // Everything but the table takes up a fixed number of lines, the table gets the rest.
+        let error_lines = 2 + state.errors.len();
+        let table_rows = height.saturating_sub(lines.len() + error_lines + 4).max(1);
+        let rows: Vec<&DashboardRow> = state
+            .rows
+            .iter()
+            .filter(|row| !state.ignore_list.matches(row.status))
+            .filter(|row| !state.hidden_classes[status_class(row.status)])
+            .filter(|row| row.name.contains(&state.filter))
+            .collect();
+        lines.push(format!("{:<8}{:<10}NAME", "STATUS", "SIZE"));
+        for row in &rows[rows.len().saturating_sub(table_rows)..] {
+            let size = row.size.map(|size| size.to_string()).unwrap_or_default();
+            let line = format!("{:<8}{:<10}{}", row.status, size, row.name);
+            lines.push(match status_class(row.status) {
+                2 => line.green().to_string(),
+                3 => line.blue().to_string(),
+                4 | 5 => line.red().to_string(),
+                _ => line.yellow().to_string(),
+            });
+        }
+        lines.push(rule.clone());
+
+        lines.push(format!("Errors ({})", state.error_count));
+        for error in &state.errors {
+            lines.push(format!("  {}", error).red().to_string());
+        }
+        lines.push(rule);
+
+        lines.push(match &state.key_mode {
+            KeyMode::Commands => HELP.to_string(),
+            KeyMode::Filter => format!("Filter: {}_   (enter to keep, esc to clear)", state.filter),
+            KeyMode::Ignore(code) => format!(
+                "Ignore status code: {}_   (enter to add, esc to cancel)",
+                code
+            ),
+        });
+
+        let lines: Vec<String> = lines
+            .into_iter()
+            .map(|line| truncate_line(&line, width))
+            .collect();
+        lines.join("\r\n")
+    }
+}
+
+/// Draws the request rate samples as a bar graph scaled to the highest sample.
+pub fn rate_graph(rate: &[usize]) -> String {
+    let highest = rate.iter().copied().max().unwrap_or(0).max(1);
+    rate.iter()
+        .map(|sample| GRAPH_BARS[sample * (GRAPH_BARS.len() - 1) / highest])
+        .collect()
+}
+
+/// Cuts a line down to `width` characters. Lines with color codes are left as they are, since
+/// the codes don't take up any room on the screen.
+fn truncate_line(line: &str, width: usize) -> String {
+    if line.contains('\x1b') {
+        return line.to_string();
+    }
+    line.chars().take(width).collect()
+}
+
+/// Returns the (width, height) of the terminal, or 80x24 if it can't be found.
+fn terminal_size() -> (usize, usize) {
+    terminal::size()
+        .map(|(width, height)| (width as usize, height as usize))
+        .unwrap_or((80, 24))
+}
+
+/// Gives the terminal back the way it was before a dashboard took it over: the normal screen, a
+/// visible cursor, and keys that are echoed and read a line at a time. Does nothing if no
+/// dashboard has the terminal, so it is safe to call more than once and from anywhere.
+pub fn restore_terminal() {
+    if TERMINAL_TAKEN.swap(false, Ordering::SeqCst) {
+        let _ = execute!(io::stdout(), cursor::Show, terminal::LeaveAlternateScreen);
+        let _ = terminal::disable_raw_mode();
+    }
+}
+
+/// Takes over the terminal for as long as it is alive, and gives it back when it is dropped.
+struct TerminalGuard;
+
+impl TerminalGuard {
+    /// Puts the terminal in raw mode, switches to the alternate screen and hides the cursor. A
+    /// panic hook is installed the first time, so a panic gives the terminal back before the
+    /// message is printed.
+    fn take() -> Result<Self, String> {
+        static PANIC_HOOK: Once = Once::new();
+        PANIC_HOOK.call_once(|| {
+            let previous = std::panic::take_hook();
+            std::panic::set_hook(Box::new(move |info| {
+                restore_terminal();
+                previous(info);
+            }));
+        });
+
+        terminal::enable_raw_mode()
+            .map_err(|err| format!("Could not take over the terminal: {}", err))?;
+        TERMINAL_TAKEN.store(true, Ordering::SeqCst);
+        let guard = TerminalGuard;
+        execute!(io::stdout(), terminal::EnterAlternateScreen, cursor::Hide)
+            .map_err(|err| format!("Could not take over the terminal: {}", err))?;
+        Ok(guard)
+    }
+}
+
+impl Drop for TerminalGuard {
+    fn drop(&mut self) {
+        restore_terminal();
+    }
+}
+
+/// Returns the byte `ScanControl::handle_key` understands for a key press, if it is one of the
+/// keys the dashboard uses.
+fn key_byte(code: KeyCode) -> Option<u8> {
+    match code {
+        KeyCode::Char(c) if c.is_ascii() => Some(c as u8),
+        KeyCode::Enter => Some(b'\r'),
+        KeyCode::Esc => Some(27),
+        KeyCode::Backspace => Some(127),
+        _ => None,
+    }
+}
+
+/// Reads key presses and hands them to `control` until `finished` is set. Ctrl-C stops the scan,
+/// since raw mode keeps it from interrupting the program.
+fn read_keys(control: Arc<ScanControl>, finished: Arc<AtomicBool>) {
+    while !finished.load(Ordering::SeqCst) {
+        match event::poll(KEY_POLL_INTERVAL) {
+            Ok(true) => {}
+            Ok(false) => continue,
+            Err(_) => return,
+        }
+        let key = match event::read() {
+            Ok(Event::Key(key)) if key.kind != KeyEventKind::Release => key,
+            Ok(_) => continue,
+            Err(_) => return,
+        };
+        if key.modifiers.contains(KeyModifiers::CONTROL) && key.code == KeyCode::Char('c') {
+            control.stop();
+        } else if let Some(byte) = key_byte(key.code) {
+            control.handle_key(byte);
+        }
+    }
+}
+
+/// A dashboard that is being drawn on the terminal.
+pub struct Dashboard {
+    control: Arc<ScanControl>,
+    finished: Arc<AtomicBool>,
+    renderer: JoinHandle<()>,
+
+    // Gives the terminal back when the dashboard stops or is dropped.
+    _terminal: TerminalGuard,
+}
+
+impl Dashboard {
+    /// Takes over the terminal and starts drawing the dashboard for `control`. Key presses are
+    /// read straight away, without waiting for enter. Returns an error if there is no terminal.
+    pub fn start(control: Arc<ScanControl>) -> Result<Self, String> {
+        if !io::stdin().is_terminal() || !io::stdout().is_terminal() {
+            return Err("The dashboard needs to be run in a terminal.".to_string());
+        }
+        let terminal = TerminalGuard::take()?;
+
+        // Key presses are read on their own thread, since reading from the terminal blocks.
+        let finished = Arc::new(AtomicBool::new(false));
+        let (keys, keys_finished) = (control.clone(), finished.clone());
+        std::thread::spawn(move || read_keys(keys, keys_finished));
+
+        // Stop the scan instead of leaving the terminal in a mess when the program is
+        // interrupted by a signal.
+        let interrupted = control.clone();
+        tokio::spawn(async move {
+            if tokio::signal::ctrl_c().await.is_ok() {
+                interrupted.stop();
+            }
+        });
+
+        let renderer = tokio::spawn(draw_until_finished(control.clone(), finished.clone()));
+        Ok(Dashboard {
+            control,
+            finished,
+            renderer,
+            _terminal: terminal,
+        })
+    }
+
+    /// Stops drawing the dashboard and gives the terminal back the way it was.
+    pub async fn stop(self) {
+        self.finished.store(true, Ordering::SeqCst);
+        let _ = self.renderer.await;
+        restore_terminal();
+        if self.control.is_stopped() {
+            println!("The scan was stopped before it finished.");
+        }
+    }
+}
+
+/// Redraws the dashboard every `REFRESH_INTERVAL` until `finished` is set.
+async fn draw_until_finished(control: Arc<ScanControl>, finished: Arc<AtomicBool>) {
+    while !finished.load(Ordering::SeqCst) {
+        control.sample_rate(Instant::now());
+        let (width, height) = terminal_size();
+        // Move to the top left corner, draw, and clear whatever is left of the last frame.
+        print!(
+            "\x1b[H{}\x1b[J",
+            control.render(width, height).replace("\r\n", "\x1b[K\r\n")
+        );
+        let _ = io::stdout().flush();
+        tokio::time::sleep(REFRESH_INTERVAL).await;
+    }
+}
@@ -0,0 +1,177 @@
+//! Comparing the saved results of two scans of the same target.
+//!
+//! A `ScanDiff` lists the pages that only appear in the newer scan, the pages that disappeared
+//! since the older scan, and the pages whose status code or size changed between the two.
+
+use crate::report::{ReportEntry, ScanReport};
+use colored::*;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fs;
+
+/// A page that was found by both scans but came back differently.
+#[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct EntryChange {
+    // The page that was requested, without a leading '/'.
+    pub path: String,
+
+    // The HTTP status code from the older scan.
+    pub old_status: u16,
+
+    // The HTTP status code from the newer scan.
+    pub new_status: u16,
+
+    // The response size from the older scan, if it was known.
+    pub old_size: Option<u64>,
+
+    // The response size from the newer scan, if it was known.
+    pub new_size: Option<u64>,
+}
+
+/// The differences between two scans. Every list is sorted by page name.
+#[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ScanDiff {
+    // The site enumerated by the older scan.
+    pub old_site: String,
+
+    // The site enumerated by the newer scan.
+    pub new_site: String,
+
+    // Pages that were only found by the newer scan.
+    pub added: Vec<ReportEntry>,
+
+    // Pages that were only found by the older scan.
+    pub removed: Vec<ReportEntry>,
+
+    // Pages found by both scans with a different status code or size.
+    pub changed: Vec<EntryChange>,
+}
+
+impl ScanDiff {
+    /// Compares the results of the `old` scan against the results of the `new` scan.
+    /// Results are matched up by their method, path and `Host` header.
+    pub fn between(old: &ScanReport, new: &ScanReport) -> Self {
+        let old_results: BTreeMap<_, &ReportEntry> = old
+            .results
+            .iter()
+            .map(|entry| (entry.key(), entry))
+            .collect();
+        let new_results: BTreeMap<_, &ReportEntry> = new
+            .results
+            .iter()
+            .map(|entry| (entry.key(), entry))
+            .collect();
+
+        let mut diff = ScanDiff {
+            old_site: old.config.site.clone(),
+            new_site: new.config.site.clone(),
+            ..Default::default()
+        };
+
+        for (key, new_entry) in &new_results {
+            match old_results.get(key) {
+                // The page wasn't found in the older scan.
+                None => diff.added.push((*new_entry).clone()),
+
+                // Both scans found the page. Only keep it if something about it changed.
+                // A size is only compared when both scans know it, so a server that stops
+                // sending a `Content-Length` header doesn't show up as a change.
+                Some(old_entry) => {
+                    let size_changed = match (old_entry.size, new_entry.size) {
+                        (Some(old_size), Some(new_size)) => old_size != new_size,
+                        _ => false,
+                    };
+                    if old_entry.status != new_entry.status || size_changed {
+                        diff.changed.push(EntryChange {
+                            path: new_entry.path.clone(),
+                            old_status: old_entry.status,
+                            new_status: new_entry.status,
+                            old_size: old_entry.size,
+                            new_size: new_entry.size,
+                        });
+                    }
+                }
+            }
+        }
+
+        // Pages from the older scan that the newer scan didn't find.
+        for (key, old_entry) in &old_results {
+            if !new_results.contains_key(key) {
+                diff.removed.push((*old_entry).clone());
+            }
+        }
+
+        diff
+    }
+
+    /// Returns true if the two scans found exactly the same pages with the same results.
+    pub fn is_empty(&self) -> bool {
+        self.added.is_empty() && self.removed.is_empty() && self.changed.is_empty()
+    }
+
+    /// Returns the differences serialized as pretty-printed JSON.
+    pub fn to_json(&self) -> String {
+        // Serializing plain structs of strings and numbers cannot fail.
+        serde_json::to_string_pretty(self).unwrap()
+    }
+
+    /// Writes the differences as JSON to the file at `path`, replacing the file if it exists.
+    pub fn write_json(&self, path: &str) -> Result<String, String> {
+        match fs::write(path, self.to_json()) {
+            Ok(_) => Ok("Successfully wrote JSON diff to ".to_string() + path),
+            Err(_err) => Err("Couldn't write JSON diff to ".to_string() + path),
+        }
+    }
+
+    /// Prints the differences to the terminal. New pages are green, removed pages are red and
+    /// changed pages are yellow.
+    pub fn display(&self) {
+        println!("---------------------------------------------------------");
+        println!("Scan Diff");
+        println!("Old site: {}", self.old_site);
+        println!("New site: {}", self.new_site);
+        println!(
+            "{} new, {} removed, {} changed",
+            self.added.len(),
+            self.removed.len(),
+            self.changed.len()
+        );
+        println!("---------------------------------------------------------");
+
+        if self.is_empty() {
+            println!("No differences found.");
+            return;
+        }
+
+        for entry in &self.added {
+            let output = format!("+ /{} --> Status: {}", entry.path, entry.status).green();
+            println!("{}", output);
+        }
+
+        for entry in &self.removed {
+            let output = format!("- /{} --> Status: {}", entry.path, entry.status).red();
+            println!("{}", output);
+        }
+
+        for change in &self.changed {
+            let output = format!(
+                "~ /{} --> Status: {} -> {}, Size: {} -> {}",
+                change.path,
+                change.old_status,
+                change.new_status,
+                display_size(change.old_size),
+                display_size(change.new_size)
+            )
+            .yellow();
+            println!("{}", output);
+        }
+    }
+}
+
+/// Formats an optional response size for the terminal.
+fn display_size(size: Option<u64>) -> String {
+    match size {
+        Some(size) => size.to_string(),
+        None => "?".to_string(),
+    }
+}
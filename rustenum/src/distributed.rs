@@ -0,0 +1,442 @@
+//! Sharing a scan between worker processes.
+//!
+//! For huge wordlists and many targets, a scan can be run by a coordinator and any number of
+//! workers. The coordinator builds every request of the scan, checks them against the scope, and
+//! splits them into shards. Workers are `rustenum worker` processes on the same machine or on
+//! other hosts, and the coordinator connects to each of them over TCP. Every worker is handed one
+//! shard at a time and runs it through the scan engine, streaming each hit back the moment it
+//! arrives, and the coordinator merges the hits into its own `Scanner::found`.
+//!
+//! The protocol is one JSON `Message` per line. The coordinator sends a `job` with the settings
+//! and the requests of a shard, and the worker answers with a `hit` for every result it keeps,
+//! followed by `done` once the shard is finished, or with `failed` if the shard couldn't be run.
+//! When a worker can't be reached or goes away, its unfinished shard is handed to another worker.
+
+use crate::analysis::SignatureSet;
+use crate::config::ScanConfig;
+use crate::results::ScanResult;
+use crate::scan::{Probe, ScanMode};
+use crate::Scanner;
+use indicatif::ProgressBar;
+use serde::{Deserialize, Serialize};
+use std::collections::VecDeque;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tokio::io::{AsyncBufRead, AsyncBufReadExt, AsyncWrite, AsyncWriteExt, BufReader};
+use tokio::net::{TcpListener, TcpStream};
+use tokio::sync::mpsc::{self, UnboundedSender};
+
+/// The number of requests in each shard when no shard size is configured.
+pub const DEFAULT_SHARD_SIZE: usize = 500;
+
+/// The address a worker listens on when none is given.
+pub const DEFAULT_WORKER_ADDRESS: &str = "127.0.0.1:7878";
+
+/// A message between the coordinator and a worker, sent as a single line of JSON.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "lowercase")]
+pub enum Message {
+    // A shard for the worker to scan, with the mode and settings to scan it with, and the
+    // signatures to check the response bodies against.
+    Job {
+        shard: usize,
+        mode: ScanMode,
+        config: Box<ScanConfig>,
+        #[serde(default)]
+        signatures: Option<SignatureSet>,
+        probes: Vec<Probe>,
+    },
+
+    // A result the worker kept while scanning a shard.
+    Hit {
+        shard: usize,
+        result: ScanResult,
+    },
+
+    // The worker finished a shard. Every response time is sent along, since they are the
+    // baseline slow pages are found with, and so is every word that never got a response.
+    Done {
+        shard: usize,
+        latencies: Vec<f64>,
+        unreachable: Vec<String>,
+    },
+
+    // The worker couldn't scan a shard with the settings it was given.
+    Failed {
+        shard: usize,
+        error: String,
+    },
+}
+
+/// Writes `message` as a line of JSON.
+async fn send_message(
+    writer: &mut (impl AsyncWrite + Unpin),
+    message: &Message,
+) -> Result<(), String> {
+    let mut line = serde_json::to_string(message)
+        .map_err(|err| format!("Could not encode a message: {}", err))?;
+    line.push('\n');
+    writer
+        .write_all(line.as_bytes())
+        .await
+        .map_err(|err| format!("Could not send a message: {}", err))
+}
+
+/// Reads the next line of JSON as a message. Returns None once the connection is closed.
+async fn read_message(reader: &mut (impl AsyncBufRead + Unpin)) -> Result<Option<Message>, String> {
+    let mut line = String::new();
+    match reader.read_line(&mut line).await {
+        Ok(0) => Ok(None),
+        Ok(_) => serde_json::from_str(&line)
+            .map(Some)
+            .map_err(|err| format!("Could not parse a message: {}", err)),
+        Err(err) => Err(format!("Could not read a message: {}", err)),
+    }
+}
+
+/// Scans the shards handed out by every coordinator that connects to `listener`, until the process
+/// is stopped. Each coordinator gets its own connection, and its shards are scanned one at a time.
+/// Every request is appended to the `audit_log` on this host, if one is given.
+pub async fn serve_worker(listener: TcpListener, audit_log: Option<String>) -> Result<(), String> {
+    loop {
+        let (stream, _) = listener
+            .accept()
+            .await
+            .map_err(|err| format!("Could not accept a connection: {}", err))?;
+        let audit_log = audit_log.clone();
+        tokio::spawn(async move {
+            if let Err(err) = serve_coordinator(stream, audit_log).await {
+                eprintln!("{}", err);
+            }
+        });
+    }
+}
+
+/// Scans every shard a single coordinator sends over `stream`, until it closes the connection.
+async fn serve_coordinator(stream: TcpStream, audit_log: Option<String>) -> Result<(), String> {
+    let (reader, mut writer) = stream.into_split();
+    let mut reader = BufReader::new(reader);
+    while let Some(message) = read_message(&mut reader).await? {
+        match message {
+            Message::Job {
+                shard,
+                mode,
+                config,
+                signatures,
+                probes,
+            } => {
+                // Only the settings for sending requests are taken from the coordinator, so a
+                // peer can't make the worker read or write files or run commands on this host.
+                let config = ScanConfig {
+                    audit_log: audit_log.clone(),
+                    output: None,
+                    save_responses: None,
+                    json: None,
+                    html: None,
+                    skipped: None,
+                    unreachable: None,
+                    ..config.for_worker()
+                };
+                scan_shard(&mut writer, shard, mode, &config, signatures, probes).await?;
+            }
+            _ => return Err("Workers only accept jobs from a coordinator.".to_string()),
+        }
+    }
+    Ok(())
+}
+
+/// Sends every probe of a shard with the scan engine, checking the bodies against `signatures`,
+/// and writes each hit to `writer` as it arrives, followed by `done`. Writes `failed` instead if
+/// the scan can't be set up, like when the worker's audit log can't be opened. The reason is only
+/// printed on this host, since it can quote files the coordinator shouldn't see.
+async fn scan_shard(
+    writer: &mut (impl AsyncWrite + Unpin),
+    shard: usize,
+    mode: ScanMode,
+    config: &ScanConfig,
+    signatures: Option<SignatureSet>,
+    probes: Vec<Probe>,
+) -> Result<(), String> {
+    let setup = config
+        .build_client()
+        .and_then(|client| Ok((client, Scanner::from_config(config, mode)?)));
+    let (client, mut scanner) = match setup {
+        Ok(setup) => setup,
+        Err(err) => {
+            eprintln!("Could not scan shard {}: {}", shard, err);
+            let error = "the worker could not set up the scan, see its log".to_string();
+            return send_message(writer, &Message::Failed { shard, error }).await;
+        }
+    };
+    scanner.signatures = signatures;
+
+    // The scan runs alongside the code that writes its hits out, so they leave as they arrive.
+    let (hits, mut received) = mpsc::unbounded_channel();
+    scanner.hits = Some(hits);
+    let progress_bar = ProgressBar::hidden();
+    let threads = config.thread_count();
+    let scan = async {
+        scanner.run(&client, probes, threads, &progress_bar).await;
+        // Dropping the sender lets the hits below run out.
+        scanner.hits = None;
+    };
+    let mut sent = Ok(());
+    let stream_hits = async {
+        while let Some(result) = received.recv().await {
+            if sent.is_ok() {
+                sent = send_message(writer, &Message::Hit { shard, result }).await;
+            }
+        }
+    };
+    tokio::join!(scan, stream_hits);
+    sent?;
+
+    let done = Message::Done {
+        shard,
+        latencies: scanner.latencies,
+        unreachable: scanner.unreachable,
+    };
+    send_message(writer, &done).await
+}
+
+/// The shards that still have to be handed to a worker.
+struct ShardQueue {
+    shards: Mutex<VecDeque<(usize, Vec<Probe>)>>,
+
+    // The shards that aren't finished yet, including the ones being scanned.
+    unfinished: AtomicUsize,
+}
+
+impl ShardQueue {
+    fn new(shards: VecDeque<(usize, Vec<Probe>)>) -> Self {
+        ShardQueue {
+            unfinished: AtomicUsize::new(shards.len()),
+            shards: Mutex::new(shards),
+        }
+    }
+
+    /// Takes the next shard to scan, if there is one waiting.
+    fn take(&self) -> Option<(usize, Vec<Probe>)> {
+        self.shards.lock().ok()?.pop_front()
+    }
+
+    /// Puts back a shard a worker didn't finish, so another worker can scan it.
+    fn put_back(&self, shard: (usize, Vec<Probe>)) {
+        if let Ok(mut shards) = self.shards.lock() {
+            shards.push_back(shard);
+        }
+    }
+
+    /// Marks a shard as finished.
+    fn finish(&self) {
+        // A shard can still finish after the queue was stopped, when there is nothing left to count.
+        let _ = self
+            .unfinished
+            .fetch_update(Ordering::SeqCst, Ordering::SeqCst, |count| {
+                count.checked_sub(1)
+            });
+    }
+
+    /// Returns the number of shards that aren't finished.
+    fn unfinished(&self) -> usize {
+        self.unfinished.load(Ordering::SeqCst)
+    }
+
+    /// Drops every shard that is still waiting, so the workers stop being handed any.
+    fn stop(&self) {
+        if let Ok(mut shards) = self.shards.lock() {
+            shards.clear();
+        }
+        self.unfinished.store(0, Ordering::SeqCst);
+    }
+}
+
+/// What the coordinator hears from the connection to a worker.
+enum WorkerEvent {
+    Hit(ScanResult),
+    Done {
+        probes: usize,
+        latencies: Vec<f64>,
+        unreachable: Vec<String>,
+    },
+    Failed(String),
+
+    // The worker couldn't be reached or went away. Its unfinished shard was put back.
+    Lost {
+        address: String,
+        error: String,
+    },
+}
+
+impl Scanner {
+    /// Shares the probes between the workers in `config`, in shards of `config.shard_size`
+    /// requests, and stores the hits they send back just like `run` stores its own. The
+    /// `progress_bar` is advanced each time a shard is finished.
+    ///
+    /// Probes that are out of the scanner's scope, or over its request budget, are never handed
+    /// out and are stored in `skipped` instead. Returns an error if a worker couldn't scan a shard,
+    /// or if every worker was lost before the scan was finished.
+    pub async fn run_distributed(
+        &mut self,
+        config: &ScanConfig,
+        probes: Vec<Probe>,
+        progress_bar: &ProgressBar,
+    ) -> Result<(), String> {
+        // The scope is checked here, so the budget covers the whole scan instead of each shard.
+        let candidates = probes.len();
+        let probes = self.scoped_probes(probes);
+        if let Some(length) = progress_bar.length() {
+            progress_bar.set_length(length.saturating_sub((candidates - probes.len()) as u64));
+        }
+
+        let shard_size = config.shard_size.unwrap_or(DEFAULT_SHARD_SIZE).max(1);
+        let shards = probes
+            .chunks(shard_size)
+            .map(|shard| shard.to_vec())
+            .enumerate()
+            .collect();
+        let queue = Arc::new(ShardQueue::new(shards));
+        let worker_config = config.for_worker();
+        let (events, mut received) = mpsc::unbounded_channel();
+        for address in &config.workers {
+            tokio::spawn(work_with(
+                address.clone(),
+                queue.clone(),
+                self.mode,
+                worker_config.clone(),
+                self.signatures.clone(),
+                events.clone(),
+            ));
+        }
+        // Every event comes from a worker's connection, so the events run out once they all end.
+        drop(events);
+
+        let mut live_output = self.open_live_output(progress_bar);
+        while let Some(event) = received.recv().await {
+            match event {
+                WorkerEvent::Hit(result) => {
+                    // A shard that is scanned again after its worker was lost sends the same hits
+                    // again, and they are only reported the first time.
+                    let known = self.found.get(&result.key()).is_some();
+                    if self.add_to_found(result.clone()) && !known {
+                        self.report_hit(&result, progress_bar, &mut live_output);
+                    }
+                }
+                WorkerEvent::Done {
+                    probes,
+                    latencies,
+                    unreachable,
+                } => {
+                    self.latencies.extend(latencies);
+                    self.unreachable.extend(unreachable);
+                    progress_bar.inc(probes as u64);
+                }
+                WorkerEvent::Failed(error) => {
+                    queue.stop();
+                    return Err(format!("A worker couldn't scan its shard: {}", error));
+                }
+                WorkerEvent::Lost { address, error } => {
+                    progress_bar.println(format!("Lost worker {}: {}", address, error));
+                }
+            }
+        }
+
+        match queue.unfinished() {
+            0 => Ok(()),
+            unfinished => Err(format!(
+                "{} shards were never scanned, since every worker was lost.",
+                unfinished
+            )),
+        }
+    }
+}
+
+/// Connects to the worker at `address` and hands it shards from the `queue` until every shard is
+/// finished, with the `signatures` the coordinator parsed. If the worker can't be reached or goes
+/// away, the shard it was scanning is put back and the worker is reported as lost.
+async fn work_with(
+    address: String,
+    queue: Arc<ShardQueue>,
+    mode: ScanMode,
+    config: ScanConfig,
+    signatures: Option<SignatureSet>,
+    events: UnboundedSender<WorkerEvent>,
+) {
+    let lost = |error: String| {
+        let _ = events.send(WorkerEvent::Lost {
+            address: address.clone(),
+            error,
+        });
+    };
+    let stream = match TcpStream::connect(&address).await {
+        Ok(stream) => stream,
+        Err(err) => return lost(err.to_string()),
+    };
+    let (reader, mut writer) = stream.into_split();
+    let mut reader = BufReader::new(reader);
+    loop {
+        let (shard, probes) = match queue.take() {
+            Some(shard) => shard,
+            None if queue.unfinished() == 0 => return,
+
+            // Another worker is still scanning, and its shard comes back if that worker is lost.
+            None => {
+                tokio::time::sleep(Duration::from_millis(50)).await;
+                continue;
+            }
+        };
+        let job = Message::Job {
+            shard,
+            mode,
+            config: Box::new(config.clone()),
+            signatures: signatures.clone(),
+            probes: probes.clone(),
+        };
+        let scanned = match send_message(&mut writer, &job).await {
+            Ok(()) => relay_shard(&mut reader, shard, probes.len(), &queue, &events).await,
+            Err(err) => Err(err),
+        };
+        if let Err(error) = scanned {
+            queue.put_back((shard, probes));
+            return lost(error);
+        }
+    }
+}
+
+/// Passes on every message the worker sends about `shard` until it is finished or failed. Returns
+/// an error if the connection breaks first.
+async fn relay_shard(
+    reader: &mut (impl AsyncBufRead + Unpin),
+    shard: usize,
+    probes: usize,
+    queue: &ShardQueue,
+    events: &UnboundedSender<WorkerEvent>,
+) -> Result<(), String> {
+    loop {
+        match read_message(reader).await? {
+            Some(Message::Hit { shard: id, result }) if id == shard => {
+                let _ = events.send(WorkerEvent::Hit(result));
+            }
+            Some(Message::Done {
+                shard: id,
+                latencies,
+                unreachable,
+            }) if id == shard => {
+                queue.finish();
+                let _ = events.send(WorkerEvent::Done {
+                    probes,
+                    latencies,
+                    unreachable,
+                });
+                return Ok(());
+            }
+            Some(Message::Failed { shard: id, error }) if id == shard => {
+                let _ = events.send(WorkerEvent::Failed(error));
+                return Ok(());
+            }
+            Some(_) => return Err("The worker sent a message about another shard.".to_string()),
+            None => return Err("The worker closed the connection.".to_string()),
+        }
+    }
+}
@@ -0,0 +1,138 @@
+//! Subdomain enumeration over DNS for the `dns` scan mode.
+//!
+//! Every word in the wordlist is prepended to the target domain and looked up with the system
+//! resolver. No HTTP requests are sent, so the results are kept in their own `DnsResults` struct
+//! instead of `Scanner::found`.
+
+use crate::Wordlist;
+use colored::*;
+use futures::{stream, StreamExt};
+use indicatif::ProgressBar;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fs;
+use std::net::IpAddr;
+
+/// The subdomains of a domain that resolved to at least one address.
+#[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct DnsResults {
+    // The domain that was enumerated.
+    pub domain: String,
+
+    // The addresses a made up subdomain resolved to, if the domain has a wildcard record.
+    // Subdomains resolving to exactly these addresses are not stored.
+    pub wildcard: Option<Vec<IpAddr>>,
+
+    // Every subdomain that resolved, with its sorted addresses.
+    pub found: BTreeMap<String, Vec<IpAddr>>,
+}
+
+/// Looks up `name` with the system resolver. Returns its sorted, de-duplicated addresses, or
+/// None if it didn't resolve.
+pub async fn resolve(name: &str) -> Option<Vec<IpAddr>> {
+    // `lookup_host` needs a port, but it isn't used for anything.
+    let addresses = tokio::net::lookup_host(format!("{}:80", name)).await.ok()?;
+    let mut addresses: Vec<IpAddr> = addresses.map(|address| address.ip()).collect();
+    addresses.sort();
+    addresses.dedup();
+    if addresses.is_empty() {
+        None
+    } else {
+        Some(addresses)
+    }
+}
+
+/// Resolves `<word>.<domain>` for every word in `wordlist`, with at most `thread_count` lookups
+/// in flight at once. The `progress_bar` is advanced after each lookup.
+pub async fn resolve_subdomains(
+    domain: &str,
+    wordlist: &Wordlist,
+    thread_count: usize,
+    progress_bar: &ProgressBar,
+) -> DnsResults {
+    let domain = domain.trim_start_matches('.');
+
+    // If a subdomain that can't exist resolves, then the domain answers for every subdomain.
+    let wildcard = resolve(&format!(
+        "rustenum-wildcard-{}.{}",
+        std::process::id(),
+        domain
+    ))
+    .await;
+
+    let lookups: Vec<(String, Option<Vec<IpAddr>>)> = stream::iter(wordlist.0.iter())
+        .map(|word| async move {
+            let name = format!("{}.{}", word, domain);
+            let addresses = resolve(&name).await;
+            progress_bar.inc(1);
+            (name, addresses)
+        })
+        .buffer_unordered(thread_count)
+        .collect()
+        .await;
+
+    let mut results = DnsResults {
+        domain: domain.to_string(),
+        wildcard: wildcard.clone(),
+        found: BTreeMap::new(),
+    };
+    for (name, addresses) in lookups {
+        if let Some(addresses) = addresses {
+            if wildcard.as_ref() != Some(&addresses) {
+                results.found.insert(name, addresses);
+            }
+        }
+    }
+    results
+}
+
+impl DnsResults {
+    /// Prints every resolved subdomain and its addresses to the terminal.
+    pub fn display(&self) {
+        println!("---------------------------------------------------------");
+        println!("Scan Results");
+        println!("Domain: {}", self.domain);
+        if let Some(wildcard) = &self.wildcard {
+            println!(
+                "Wildcard record found, ignoring subdomains resolving to {:?}",
+                wildcard
+            );
+        }
+        println!("---------------------------------------------------------");
+
+        for (name, addresses) in &self.found {
+            let output = format!("{} --> {}", name, join_addresses(addresses)).green();
+            println!("{}", output);
+        }
+    }
+
+    /// Writes the results as JSON to the file at `path`, replacing the file if it exists.
+    pub fn write_json(&self, path: &str) -> Result<String, String> {
+        // Serializing plain structs of strings and addresses cannot fail.
+        match fs::write(path, serde_json::to_string_pretty(self).unwrap()) {
+            Ok(_) => Ok("Successfully wrote JSON results to ".to_string() + path),
+            Err(_err) => Err("Couldn't write JSON results to ".to_string() + path),
+        }
+    }
+
+    /// Writes one line per resolved subdomain to the file at `path`, replacing the file if it exists.
+    pub fn write_results_to_file(&self, path: &str) -> Result<String, String> {
+        let mut contents = String::new();
+        for (name, addresses) in &self.found {
+            contents += &format!("{} -> {}\n", name, join_addresses(addresses));
+        }
+        match fs::write(path, contents) {
+            Ok(_) => Ok("Successfully wrote results to ".to_string() + path),
+            Err(_err) => Err("Couldn't write results to ".to_string() + path),
+        }
+    }
+}
+
+/// Formats a list of addresses as a comma separated string.
+fn join_addresses(addresses: &[IpAddr]) -> String {
+    addresses
+        .iter()
+        .map(|address| address.to_string())
+        .collect::<Vec<String>>()
+        .join(", ")
+}
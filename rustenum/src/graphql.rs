@@ -0,0 +1,314 @@
+//! Detecting GraphQL endpoints and checking whether they answer introspection queries.
+//!
+//! GraphQL APIs are served from a single path, usually one like `/graphql`, `/gql` or
+//! `/graphiql`. Once a `dir` scan is done, every result at one of the common GraphQL paths is sent
+//! the smallest possible query, `{__typename}`, as a JSON POST. A result that answers with a
+//! GraphQL response is then sent the standard introspection query, and when introspection is
+//! enabled the schema it returns is saved to a file, since it documents every type, query and
+//! mutation the API has. Only queries are sent, so nothing on the server is changed.
+
+use crate::results::{ResultKey, ScanResult};
+use crate::scan::{send_probe, Probe};
+use crate::{result_line, Scanner};
+use futures::stream::{self, StreamExt};
+use indicatif::ProgressBar;
+use reqwest::{Client, Url};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use std::collections::HashSet;
+use std::fs;
+use std::path::Path;
+
+/// The directory schemas are saved to when none is set.
+pub const DEFAULT_SCHEMA_DIR: &str = "graphql-schemas";
+
+/// The last segments of the paths GraphQL endpoints are commonly served from. A result is checked
+/// when its path ends with one of them, so "/api/graphql" and "/v1/graphql" are checked too.
+pub const GRAPHQL_PATHS: [&str; 8] = [
+    "graphql",
+    "graphql.php",
+    "graphql/console",
+    "graphiql",
+    "gql",
+    "query",
+    "playground",
+    "altair",
+];
+
+/// The query sent to find out if a path is a GraphQL endpoint. Every GraphQL server answers it.
+pub const TYPENAME_QUERY: &str = "{__typename}";
+
+/// The standard introspection query, which asks for every type of the schema with its fields,
+/// arguments, enum values and interfaces, and for the directives.
+pub const INTROSPECTION_QUERY: &str = "query IntrospectionQuery { __schema { queryType { name } \
+    mutationType { name } subscriptionType { name } types { ...FullType } directives { name \
+    description locations args { ...InputValue } } } } fragment FullType on __Type { kind name \
+    description fields(includeDeprecated: true) { name description args { ...InputValue } type { \
+    ...TypeRef } isDeprecated deprecationReason } inputFields { ...InputValue } interfaces { \
+    ...TypeRef } enumValues(includeDeprecated: true) { name description isDeprecated \
+    deprecationReason } possibleTypes { ...TypeRef } } fragment InputValue on __InputValue { name \
+    description type { ...TypeRef } defaultValue } fragment TypeRef on __Type { kind name ofType { \
+    kind name ofType { kind name ofType { kind name ofType { kind name ofType { kind name ofType { \
+    kind name ofType { kind name } } } } } } } }";
+
+/// A GraphQL endpoint found among the results of a scan.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct GraphqlEndpoint {
+    // The key of the result the endpoint was found at in `Scanner::found`.
+    pub result: ResultKey,
+
+    // The url the queries were sent to.
+    pub url: String,
+
+    // The HTTP status code of the answer to the `__typename` query.
+    pub status: u16,
+
+    // Whether the endpoint answered the introspection query with its schema.
+    pub introspection: bool,
+
+    // The number of types in the schema, when introspection is enabled.
+    pub types: Option<usize>,
+
+    // The file the schema was saved to, when introspection is enabled.
+    pub schema_file: Option<String>,
+}
+
+impl GraphqlEndpoint {
+    /// Returns how the endpoint is shown in the terminal, like
+    /// "POST http://example.com/graphql (GraphQL, introspection enabled: 42 types)".
+    pub fn display_name(&self) -> String {
+        format!(
+            "POST {} (GraphQL, {})",
+            self.url,
+            self.introspection_label()
+        )
+    }
+
+    /// Returns whether introspection is enabled, the way it is tagged on the endpoint's result.
+    pub fn introspection_label(&self) -> String {
+        match self.types {
+            Some(types) if self.introspection => {
+                format!("introspection enabled: {} types", types)
+            }
+            _ => "introspection disabled".to_string(),
+        }
+    }
+}
+
+/// Returns true if the path of `url` ends with one of the `GRAPHQL_PATHS`.
+pub fn is_graphql_path(url: &str) -> bool {
+    let path = match Url::parse(url) {
+        Ok(url) => url.path().to_lowercase(),
+        Err(_) => return false,
+    };
+    let path = path.trim_matches('/');
+    GRAPHQL_PATHS
+        .iter()
+        .any(|common| path == *common || path.ends_with(&format!("/{}", common)))
+}
+
+/// Returns the body of a JSON POST that sends `query`.
+pub fn query_body(query: &str) -> String {
+    json!({ "query": query }).to_string()
+}
+
+/// Returns true if `body` is a GraphQL response: a JSON object with the `__typename` of the query
+/// in its data, or with a list of errors that have a message, which is how a server that doesn't
+/// allow the query still answers.
+pub fn is_graphql_response(body: &[u8]) -> bool {
+    let response: Value = match serde_json::from_slice(body) {
+        Ok(response) => response,
+        Err(_) => return false,
+    };
+    if response["data"]["__typename"].is_string() {
+        return true;
+    }
+    match response["errors"].as_array() {
+        Some(errors) => {
+            !errors.is_empty() && errors.iter().all(|error| error["message"].is_string())
+        }
+        None => false,
+    }
+}
+
+/// Returns the number of types in the schema of an answer to the introspection query, or None if
+/// the answer has no schema, like when introspection is disabled.
+pub fn schema_types(body: &[u8]) -> Option<usize> {
+    let response: Value = serde_json::from_slice(body).ok()?;
+    response["data"]["__schema"]["types"]
+        .as_array()
+        .map(|types| types.len())
+}
+
+/// Returns the name of the file the schema of the endpoint at `url`, sent with the `Host` header
+/// `host`, is saved to: the host and the url without its scheme, with every character that isn't
+/// safe in a file name replaced by '_'. The host keeps the endpoints of virtual hosts on the same
+/// url in different files.
+pub fn schema_file_name(url: &str, host: Option<&str>) -> String {
+    let without_scheme = url.split_once("://").map_or(url, |(_, rest)| rest);
+    let readable = match host {
+        Some(host) => format!("{}_{}", host, without_scheme),
+        None => without_scheme.to_string(),
+    };
+    let readable: String = readable
+        .trim_end_matches('/')
+        .chars()
+        .map(|c| {
+            if c.is_ascii_alphanumeric() || c == '.' || c == '-' {
+                c
+            } else {
+                '_'
+            }
+        })
+        .collect();
+    format!("{}.json", readable)
+}
+
+/// Saves the answer to the introspection query for the endpoint at `url`, sent with the `Host`
+/// header `host`, to `dir`, pretty printed, creating the directory if it doesn't exist. Returns
+/// the path of the file.
+fn save_schema(dir: &str, url: &str, host: Option<&str>, body: &[u8]) -> Result<String, String> {
+    let schema: Value = serde_json::from_slice(body)
+        .map_err(|err| format!("Could not parse the schema of {}: {}", url, err))?;
+    fs::create_dir_all(dir)
+        .map_err(|err| format!("Could not create schema directory {}: {}", dir, err))?;
+    let path = Path::new(dir).join(schema_file_name(url, host));
+    let contents = serde_json::to_string_pretty(&schema).map_err(|err| err.to_string())?;
+    fs::write(&path, contents)
+        .map_err(|err| format!("Could not save the schema to {}: {}", path.display(), err))?;
+    Ok(path.display().to_string())
+}
+
+/// Returns a probe that POSTs `query` to the endpoint of `result`.
+fn query_probe(result: &ScanResult, query: &str) -> Probe {
+    Probe {
+        name: result.name.clone(),
+        url: result.url.clone(),
+        host: result.host.clone(),
+        word: result.name.clone(),
+        form: None,
+        method: None,
+        json: Some(query_body(query)),
+    }
+}
+
+impl Scanner {
+    /// Sends `query` to the endpoint of every result in `results`, `thread_count` at a time, and
+    /// returns the results with the status code and body of their answers. Results whose query is
+    /// out of scope, or that never got an answer, are left out.
+    async fn send_queries(
+        &mut self,
+        client: &Client,
+        results: Vec<ScanResult>,
+        query: &str,
+        thread_count: usize,
+        progress_bar: &ProgressBar,
+    ) -> Vec<(ScanResult, u16, Vec<u8>)> {
+        let results: Vec<ScanResult> = results
+            .into_iter()
+            .filter(|result| self.check_scope(&result.url).is_ok())
+            .collect();
+
+        let audit = self.audit.clone();
+        let policy = self.retry_policy.clone();
+        stream::iter(results)
+            .map(|result| {
+                let (audit, policy) = (audit.as_deref(), &policy);
+                let probe = query_probe(&result, query);
+                async move {
+                    let outcome = send_probe(client, probe, policy, true, audit).await;
+                    progress_bar.inc(1);
+                    let status = outcome.result?.status;
+                    Some((result, status, outcome.body.unwrap_or_default()))
+                }
+            })
+            .buffered(thread_count)
+            .filter_map(|answer| async move { answer })
+            .collect()
+            .await
+    }
+
+    /// Checks every result at one of the `GRAPHQL_PATHS` for a GraphQL endpoint, sending
+    /// `thread_count` queries at a time. Every endpoint found is sent the introspection query, and
+    /// the schema is saved to `schema_dir` when it answers. Queries are checked against the scope
+    /// like any other request. Endpoints are stored in `graphql_endpoints`, printed above the
+    /// `progress_bar`, and tagged on their result in `found`. A schema that can't be saved is
+    /// reported above the `progress_bar`, and the endpoint is kept without a schema file. Returns
+    /// the number of endpoints found.
+    pub async fn probe_graphql(
+        &mut self,
+        client: &Client,
+        schema_dir: &str,
+        thread_count: usize,
+        progress_bar: &ProgressBar,
+    ) -> usize {
+        // The same endpoint is only queried once, even if it was found with several methods.
+        let mut urls = HashSet::new();
+        let candidates: Vec<ScanResult> = self
+            .found
+            .iter()
+            .filter(|result| is_graphql_path(&result.url))
+            .filter(|result| urls.insert((result.url.clone(), result.host.clone())))
+            .cloned()
+            .collect();
+        progress_bar.set_length(candidates.len() as u64);
+
+        let detected: Vec<(ScanResult, u16)> = self
+            .send_queries(
+                client,
+                candidates,
+                TYPENAME_QUERY,
+                thread_count,
+                progress_bar,
+            )
+            .await
+            .into_iter()
+            .filter(|(_, _, body)| is_graphql_response(body))
+            .map(|(result, status, _)| (result, status))
+            .collect();
+
+        progress_bar.inc_length(detected.len() as u64);
+        let endpoints = detected.iter().map(|(result, _)| result.clone()).collect();
+        let schemas = self
+            .send_queries(
+                client,
+                endpoints,
+                INTROSPECTION_QUERY,
+                thread_count,
+                progress_bar,
+            )
+            .await;
+
+        for (result, status) in &detected {
+            let schema = schemas
+                .iter()
+                .find(|(answered, _, _)| answered.key() == result.key())
+                .and_then(|(_, _, body)| Some((schema_types(body)?, body)));
+            let mut endpoint = GraphqlEndpoint {
+                result: result.key(),
+                url: result.url.clone(),
+                status: *status,
+                introspection: schema.is_some(),
+                types: None,
+                schema_file: None,
+            };
+            if let Some((types, body)) = schema {
+                endpoint.types = Some(types);
+                match save_schema(schema_dir, &result.url, result.host.as_deref(), body) {
+                    Ok(file) => endpoint.schema_file = Some(file),
+                    Err(err) => progress_bar.println(err),
+                }
+            }
+            progress_bar.println(result_line(&endpoint.display_name(), *status).to_string());
+
+            if let Some(original) = self.found.get(&endpoint.result) {
+                let mut tagged = original.clone();
+                tagged.tags.push("GraphQL".to_string());
+                tagged.tags.push(endpoint.introspection_label());
+                self.found.insert(tagged);
+            }
+            self.graphql_endpoints.push(endpoint);
+        }
+        detected.len()
+    }
+}
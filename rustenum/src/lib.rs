@@ -2,24 +2,301 @@
 //!
 //! Tristan Gomez - Winter 2022
 
+pub mod analysis;
+pub mod audit;
+pub mod bench;
+pub mod bypass;
+pub mod config;
+pub mod dashboard;
+pub mod diff;
+pub mod distributed;
+pub mod dns;
+pub mod graphql;
+pub mod openapi;
+pub mod params;
+pub mod plugins;
+pub mod report;
+pub mod responses;
+pub mod results;
+pub mod retry;
+pub mod scan;
+pub mod scope;
+pub mod services;
+pub mod target;
+pub mod timing;
+pub mod wordlist;
+
+use crate::analysis::{finding_names, BodyFinding, SignatureSet};
+use crate::audit::AuditLog;
+use crate::bypass::BypassResult;
+use crate::config::ScanConfig;
+use crate::dashboard::ScanControl;
+use crate::graphql::GraphqlEndpoint;
+use crate::openapi::ApiEndpoint;
+use crate::params::ParamMethod;
+use crate::plugins::{CommandPlugin, PluginSet, ResponsePlugin};
+use crate::responses::{SavedResponse, INDEX_FILE};
+use crate::results::{ResultStore, ScanResult};
+use crate::retry::RetryPolicy;
+use crate::scan::{Probe, ScanMode, FUZZ_KEYWORD};
+use crate::scope::{Scope, SkipReason, SkippedRequest};
+use crate::target::{with_scheme, Target};
+use crate::timing::{LatencySummary, DEFAULT_SLOW_FACTOR};
 use colored::*;
+use indicatif::ProgressBar;
 use reqwest::Url;
-use std::collections::BTreeMap;
+use serde::{Deserialize, Serialize};
+use std::fmt;
 use std::fs::File;
 use std::fs::OpenOptions;
 use std::io::prelude::Write;
-use std::io::{self, BufRead};
 use std::sync::Arc;
+use tokio::sync::mpsc::UnboundedSender;
 
 /// A generic function to call when an error occurs. It takes a string slice as its sole argument which is displayed to the user.
 /// Then the program gracefully ends with an error status code.
 pub fn error(e: &str) -> ! {
+    // Exiting skips every destructor, so the terminal is given back here if a dashboard has it.
+    dashboard::restore_terminal();
     eprintln!("{}", e);
     std::process::exit(1);
 }
 
+/// A single rule of a `StatusMatcher`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StatusRule {
+    // Exactly this status code, like "404".
+    Code(u16),
+
+    // Every status code from the first to the second, inclusive, like "400-499".
+    Range(u16, u16),
+
+    // Every status code starting with this digit, like "4xx".
+    Class(u16),
+}
+
+impl StatusRule {
+    /// Parses a single rule: an exact code, a range or a class.
+    pub fn parse(text: &str) -> Result<Self, String> {
+        let text = text.trim();
+        let code = |text: &str| -> Result<u16, String> {
+            match text.parse::<u16>() {
+                Ok(code) if (100..=599).contains(&code) => Ok(code),
+                _ => Err(format!(
+                    "`{}` isn't a status code in the range of 100 to 599.",
+                    text
+                )),
+            }
+        };
+
+        if let Some((start, end)) = text.split_once('-') {
+            let (start, end) = (code(start)?, code(end)?);
+            if start > end {
+                return Err(format!("The range `{}` ends before it starts.", text));
+            }
+            return Ok(StatusRule::Range(start, end));
+        }
+        let lowercase = text.to_lowercase();
+        if let Some(class) = lowercase.strip_suffix("xx") {
+            return match class.parse::<u16>() {
+                Ok(class) if (1..=5).contains(&class) => Ok(StatusRule::Class(class)),
+                _ => Err(format!("`{}` isn't a status class from 1xx to 5xx.", text)),
+            };
+        }
+        code(text).map(StatusRule::Code)
+    }
+
+    /// Returns true if `status` is covered by the rule.
+    pub fn matches(&self, status: u16) -> bool {
+        match self {
+            StatusRule::Code(code) => status == *code,
+            StatusRule::Range(start, end) => (*start..=*end).contains(&status),
+            StatusRule::Class(class) => status / 100 == *class,
+        }
+    }
+}
+
+impl fmt::Display for StatusRule {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            StatusRule::Code(code) => write!(f, "{}", code),
+            StatusRule::Range(start, end) => write!(f, "{}-{}", start, end),
+            StatusRule::Class(class) => write!(f, "{}xx", class),
+        }
+    }
+}
+
+/// A set of HTTP status codes, described by exact codes ("404"), ranges ("400-499") and classes
+/// ("4xx"). Negated rules ("!403") carve exceptions out of the set, so "4xx,!403" matches every
+/// client error except 403. A matcher without any rules that aren't negated matches nothing.
+#[derive(Default, Debug, Clone, PartialEq, Eq)]
+pub struct StatusMatcher {
+    // The rules a status code has to match one of.
+    rules: Vec<StatusRule>,
+
+    // The negated rules. A status code matching one of these never matches.
+    exceptions: Vec<StatusRule>,
+}
+
+impl StatusMatcher {
+    /// Make a new matcher that matches nothing.
+    pub fn new() -> Self {
+        StatusMatcher::default()
+    }
+
+    /// Parses a comma separated list of rules, like "4xx,!403,500-599".
+    pub fn parse(text: &str) -> Result<Self, String> {
+        let mut matcher = StatusMatcher::new();
+        for rule in text.split(',').filter(|rule| !rule.trim().is_empty()) {
+            matcher.add_rule(rule)?;
+        }
+        Ok(matcher)
+    }
+
+    /// Parses a single rule and adds it to the matcher. A leading '!' adds it as an exception.
+    pub fn add_rule(&mut self, text: &str) -> Result<(), String> {
+        match text.trim().strip_prefix('!') {
+            Some(negated) => self.exceptions.push(StatusRule::parse(negated)?),
+            None => self.rules.push(StatusRule::parse(text)?),
+        }
+        Ok(())
+    }
+
+    /// Adds an exact status code to the matcher if it doesn't match it already. An exception for
+    /// the code is removed.
+    pub fn add_code(&mut self, status: u16) {
+        self.exceptions
+            .retain(|rule| *rule != StatusRule::Code(status));
+        if !self.matches(status) {
+            self.rules.push(StatusRule::Code(status));
+        }
+    }
+
+    /// Adds every rule and exception of `other` to the matcher.
+    pub fn extend(&mut self, other: &StatusMatcher) {
+        for rule in &other.rules {
+            if !self.rules.contains(rule) {
+                self.rules.push(*rule);
+            }
+        }
+        for rule in &other.exceptions {
+            if !self.exceptions.contains(rule) {
+                self.exceptions.push(*rule);
+            }
+        }
+    }
+
+    /// Returns true if the matcher has no rules at all.
+    pub fn is_empty(&self) -> bool {
+        self.rules.is_empty() && self.exceptions.is_empty()
+    }
+
+    /// Returns true if `status` is in the set.
+    pub fn matches(&self, status: u16) -> bool {
+        self.rules.iter().any(|rule| rule.matches(status))
+            && !self.exceptions.iter().any(|rule| rule.matches(status))
+    }
+
+    /// Returns true if `status` is allowed by the matcher used as an allow-list: a matcher without
+    /// any rules allows every status code, otherwise the code has to match.
+    pub fn allows(&self, status: u16) -> bool {
+        self.rules.is_empty() || self.matches(status)
+    }
+
+    /// Returns every rule as it would be written, exceptions last.
+    pub fn rule_texts(&self) -> Vec<String> {
+        self.rules
+            .iter()
+            .map(|rule| rule.to_string())
+            .chain(self.exceptions.iter().map(|rule| format!("!{}", rule)))
+            .collect()
+    }
+}
+
+impl fmt::Display for StatusMatcher {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "[{}]", self.rule_texts().join(", "))
+    }
+}
+
+impl From<Vec<u16>> for StatusMatcher {
+    fn from(codes: Vec<u16>) -> Self {
+        let mut matcher = StatusMatcher::new();
+        for code in codes {
+            matcher.add_code(code);
+        }
+        matcher
+    }
+}
+
+/// Matchers are written as a list of rules, like ["404", "5xx", "!503"].
+impl Serialize for StatusMatcher {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        self.rule_texts().serialize(serializer)
+    }
+}
+
+/// Matchers can be read from a list of plain codes and rules, like [404, "5xx"], or from a single
+/// comma separated string of rules. Lists of codes were written by older versions.
+impl<'de> Deserialize<'de> for StatusMatcher {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        #[derive(Deserialize)]
+        #[serde(untagged)]
+        enum Rule {
+            Code(u16),
+            Text(String),
+        }
+
+        #[derive(Deserialize)]
+        #[serde(untagged)]
+        enum Rules {
+            List(Vec<Rule>),
+            Text(String),
+        }
+
+        let matcher = match Rules::deserialize(deserializer)? {
+            Rules::Text(text) => StatusMatcher::parse(&text),
+            Rules::List(rules) => {
+                let mut matcher = StatusMatcher::new();
+                rules
+                    .iter()
+                    .try_for_each(|rule| match rule {
+                        Rule::Code(code) => matcher.add_rule(&code.to_string()),
+                        Rule::Text(text) => matcher.add_rule(text),
+                    })
+                    .map(|_| matcher)
+            }
+        };
+        matcher.map_err(serde::de::Error::custom)
+    }
+}
+
+/// Returns true if a response with `status` is left out of the results, because it is matched by
+/// the `ignore` list or isn't allowed by the `only` list.
+pub fn is_ignored(ignore: &StatusMatcher, only: &StatusMatcher, status: u16) -> bool {
+    ignore.matches(status) || !only.allows(status)
+}
+
+/// Formats a result for the terminal, with a color that depends on its HTTP status code.
+pub fn result_line(page: &str, status_code: u16) -> ColoredString {
+    let output = format!("{} --> Status: {}", page, status_code);
+
+    // Success codes are green, redirects are blue because they are something to look into
+    // further, and client & server errors are red since they are not immediately useful to the
+    // user. Anything else is unusual, so it is yellow to indicate caution.
+    if StatusRule::Class(2).matches(status_code) {
+        output.green()
+    } else if StatusRule::Class(3).matches(status_code) {
+        output.blue()
+    } else if StatusRule::Range(400, 599).matches(status_code) {
+        output.red()
+    } else {
+        output.yellow()
+    }
+}
+
 /// The `Wordlist` tuple struct contains the contents of the user provided wordlist file in a vector of strings.
-#[derive(Default, Debug, Clone)]
+#[derive(Default, Debug, Clone, Serialize, Deserialize)]
 pub struct Wordlist(pub Vec<String>);
 
 impl Wordlist {
@@ -28,31 +305,15 @@ impl Wordlist {
         Wordlist(Vec::new())
     }
 
-    /// Fill the wordlist with the contents of the file at arg 'path', and
-    /// return the new wordlist.
+    /// Fill the wordlist with the contents of the file at arg 'path', read the way
+    /// `Wordlist::from_file` reads it, and return the new wordlist. Exits with an error if the
+    /// file can't be read.
     pub fn extend_from_file(&mut self, path: String) -> Self {
-        // Attempt to open the file at the provided `path`.
-        let _file = match File::open(path) {
-            // File successfully opened!
-            Ok(file_handle) => {
-                // Attempt to read in all words from file, line-by-line. Then attempt to push them to the vector of strings.
-                let lines = io::BufReader::new(file_handle).lines();
-                for line in lines {
-                    if let Ok(word) = line {
-                        self.0.push(word.clone());
-                    } else {
-                        error("Error when reading from file. Please check the contents of the provided wordlist.");
-                    }
-                }
-                return self.clone();
-            }
-
-            // File could not be opened. Call the `error()` with a diagnostic
-            // message and exit gracefully.
-            Err(_err) => {
-                error("Error when handling file. Please check the provided file path");
-            }
-        };
+        match Wordlist::from_file(&path) {
+            Ok(words) => self.merge(words),
+            Err(err) => error(&err),
+        }
+        self.clone()
     }
 
     /// Returns the number of stored words.
@@ -70,25 +331,147 @@ impl Wordlist {
     }
 }
 
-/// This struct represents the scanner/scanning process. It contains the Wordlist struct, a vec<u16> that represents HTTP status codes to ignore,
-/// a BTreeMap of results from HTTP GET requests during the enumeration procerss, and a string that is the base url for the site in question.
-#[derive(Default, Debug)]
+/// Extra information about a response, kept with its result so it can be used by the report
+/// output formats.
+#[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ResponseDetails {
+    // The value of the `Location` header, if the response was a redirect.
+    pub location: Option<String>,
+
+    // The size of the response body in bytes.
+    pub content_length: Option<u64>,
+
+    // Milliseconds from sending the request until the response headers arrived (time to first byte).
+    pub ttfb_ms: Option<f64>,
+
+    // Milliseconds from sending the request until the whole response body was read.
+    pub total_ms: Option<f64>,
+
+    // The signatures that matched the response body, if the body was analyzed.
+    #[serde(default)]
+    pub findings: Vec<BodyFinding>,
+
+    // The name of the file the raw response was saved to in the response directory.
+    #[serde(default)]
+    pub saved_as: Option<String>,
+}
+
+/// This struct represents the scanner/scanning process. It contains the Wordlist struct, a matcher of HTTP status codes to ignore,
+/// a store of results from HTTP requests during the enumeration procerss, and a string that is the base url for the site in question.
+#[derive(Default, Debug, Serialize, Deserialize)]
 pub struct Scanner {
     // Words to look for in the scan
     pub wordlist: Wordlist,
 
-    // Ignore pages that return HTTP status codes within this list.
-    pub status_code_ignore_list: Vec<u16>,
+    // Ignore pages that return HTTP status codes matched by this.
+    pub status_code_ignore_list: StatusMatcher,
+
+    // When this isn't empty, only pages that return HTTP status codes matched by this are kept.
+    #[serde(default)]
+    pub status_code_match_list: StatusMatcher,
 
-    // The results of the scan. If a HTTP GET request returns a status_code NOT in the
-    // 'status_code_ignore_list' then add the page name and its corresponding HTTP status code.
-    pub found: BTreeMap<String, u16>,
+    // The results of the scan. If a HTTP request returns a status_code NOT in the
+    // 'status_code_ignore_list' then a record of the request and its response is added.
+    pub found: ResultStore,
 
     // The base url to enumerate.
     pub site: Arc<String>,
 
     // File extensions to check
     pub extension_list: Wordlist,
+
+    // The kind of scan being run.
+    #[serde(default)]
+    pub mode: ScanMode,
+
+    // The total response time in milliseconds of every response received, including the
+    // ignored ones. These are the baseline used to find slow endpoints.
+    #[serde(default)]
+    pub latencies: Vec<f64>,
+
+    // How many times slower than the median response a page has to be to be flagged as slow.
+    // `DEFAULT_SLOW_FACTOR` is used when this isn't set.
+    #[serde(default)]
+    pub slow_factor: Option<f64>,
+
+    // Which failed requests are sent again, and how long to wait between attempts.
+    #[serde(default)]
+    pub retry_policy: RetryPolicy,
+
+    // Words that never got a response, even after every retry. They can be written to a file
+    // with `write_unreachable` and used as the wordlist of a later scan.
+    #[serde(default)]
+    pub unreachable: Vec<String>,
+
+    // How the candidate parameters of a `params` scan are sent.
+    #[serde(default)]
+    pub param_method: ParamMethod,
+
+    // The number of candidate parameters sent in each request of a `params` scan.
+    // `DEFAULT_BATCH_SIZE` is used when this isn't set.
+    #[serde(default)]
+    pub batch_size: Option<usize>,
+
+    // The signatures every response body is checked against. Bodies aren't analyzed when
+    // this isn't set.
+    #[serde(skip)]
+    pub signatures: Option<SignatureSet>,
+
+    // The directory the raw responses of matched results are saved to. Responses aren't saved
+    // when this isn't set.
+    #[serde(default)]
+    pub response_dir: Option<String>,
+
+    // Every response that was saved to `response_dir`.
+    #[serde(default)]
+    pub saved_responses: Vec<SavedResponse>,
+
+    // The file hits are appended to the moment they arrive during a scan.
+    #[serde(default)]
+    pub live_output: Option<String>,
+
+    // The state shared with a dashboard, if the scan is run with one. The scan records every
+    // response in it and can be paused, slowed down or stopped through it.
+    #[serde(skip)]
+    pub control: Option<Arc<ScanControl>>,
+
+    // The paths the scan may request and the most requests it may send.
+    #[serde(skip)]
+    pub scope: Scope,
+
+    // Every candidate request that was out of scope, with the reason it wasn't sent.
+    #[serde(default)]
+    pub skipped: Vec<SkippedRequest>,
+
+    // The number of requests that were let through the scope, counted against its budget.
+    // Retries of a request aren't counted again.
+    #[serde(default)]
+    pub sent_requests: usize,
+
+    // The log every request is appended to, if one was asked for.
+    #[serde(skip)]
+    pub audit: Option<Arc<AuditLog>>,
+
+    // Every hit is also sent here the moment it arrives, if it is set. Workers use this to stream
+    // their hits back to the coordinator.
+    #[serde(skip)]
+    pub hits: Option<UnboundedSender<ScanResult>>,
+
+    // The plugins every response of a `dir`, `vhost` or `fuzz` scan is given to.
+    #[serde(skip)]
+    pub plugins: PluginSet,
+
+    // The variants of forbidden results that got another status code, from `probe_bypasses`.
+    #[serde(default)]
+    pub bypasses: Vec<BypassResult>,
+
+    // The documented API operations the scan requested, from `api_probes`.
+    #[serde(default)]
+    pub api_endpoints: Vec<ApiEndpoint>,
+
+    // The GraphQL endpoints found among the results, from `probe_graphql`.
+    #[serde(default)]
+    pub graphql_endpoints: Vec<GraphqlEndpoint>,
 }
 
 impl Scanner {
@@ -96,144 +479,277 @@ impl Scanner {
     pub fn new() -> Self {
         Scanner {
             wordlist: Wordlist::new(),
-            status_code_ignore_list: Vec::new(),
-            found: BTreeMap::new(),
+            status_code_ignore_list: StatusMatcher::new(),
+            status_code_match_list: StatusMatcher::new(),
+            found: ResultStore::new(),
             site: Arc::new(String::new()),
             extension_list: Wordlist::new(),
+            mode: ScanMode::Dir,
+            latencies: Vec::new(),
+            slow_factor: None,
+            retry_policy: RetryPolicy::default(),
+            unreachable: Vec::new(),
+            param_method: ParamMethod::Get,
+            batch_size: None,
+            signatures: None,
+            response_dir: None,
+            saved_responses: Vec::new(),
+            live_output: None,
+            control: None,
+            scope: Scope::default(),
+            skipped: Vec::new(),
+            sent_requests: 0,
+            audit: None,
+            hits: None,
+            plugins: PluginSet::default(),
+            bypasses: Vec::new(),
+            api_endpoints: Vec::new(),
+            graphql_endpoints: Vec::new(),
         }
     }
 
+    /// Make a new scanner that is ready to run a `mode` scan from the settings in `config`. The site
+    /// is parsed, every wordlist is loaded, 404 and the configured status codes are ignored, and the
+    /// configured extensions are added to the wordlist. Returns an error if the url cannot be parsed,
+    /// a wordlist or the signatures file can't be loaded, or the audit log can't be opened.
+    pub fn from_config(config: &ScanConfig, mode: ScanMode) -> Result<Self, String> {
+        let mut scanner = Scanner::new();
+        scanner.mode = mode;
+        scanner.slow_factor = config.slow_factor;
+        scanner.retry_policy = config.retry_policy();
+        scanner.param_method = config.param_method.unwrap_or_default();
+        scanner.batch_size = config.batch_size;
+        scanner.signatures = config.signature_set()?;
+        scanner.response_dir = config.save_responses.clone();
+        scanner.live_output = config.output.clone();
+        scanner.scope = config.scope()?;
+        scanner.audit = config.audit_log()?.map(Arc::new);
+        for command in &config.plugins {
+            scanner.add_plugin(CommandPlugin::start(command)?);
+        }
+
+        // A scan of a target list without a url shows the first target as its site.
+        let url = match &config.url {
+            Some(url) => Some(url.clone()),
+            None => config.target_urls()?.into_iter().next(),
+        };
+        if let Some(url) = &url {
+            match mode {
+                ScanMode::Fuzz => scanner.try_add_fuzz_template(url)?,
+                ScanMode::Dir | ScanMode::Vhost | ScanMode::Params => {
+                    scanner.try_add_site(url)?;
+                }
+            }
+        }
+
+        for path in &config.wordlists {
+            scanner.build_wordlist_from_file(path)?;
+        }
+
+        scanner.use_default_ignore_list();
+        scanner.status_code_ignore_list.extend(&config.ignore);
+        scanner.status_code_match_list = config.match_status.clone();
+
+        if !config.extensions.is_empty() {
+            scanner.add_extensions_to_wordlist(&config.extensions.join(","));
+        }
+
+        Ok(scanner)
+    }
+
     /// Attempt to add the provided url to the `site` data member. If the provided
-    /// url is successfully parsed by the `reqwest::Url` struct then add it to self.site.
+    /// url is successfully parsed as a `Target` then its normalized form is added to self.site.
     ///
-    /// If the provided url cannot be parsed by the `reqwest::Url` struct than it cannot be
-    /// used by reqwest, so we have to return an error.
-    pub fn try_add_site(&mut self, provided_url: &str) -> Result<&str, &str> {
-        // Make a new `host_addr` String and fill it with the provided_url argument.
-        let mut host_addr = String::new();
-        host_addr += provided_url;
-
-        // If the provided url doesn't have `http://` or `https://` prepended to it then
-        // we should add it. Most websites like `google.com` *should* still work, but
-        // an IP address will *NOT* work. Adding an http(s) scheme will allow an ip address
-        // to be successfully parsed. To make it easy on me, I will prepend every provided url
-        // with "http://" if a scheme is not provided.
-        if !provided_url.starts_with("http://") || !provided_url.starts_with("https://") {
-            host_addr.insert_str(0, "http://");
-        }
-
-        // Attempt to parse the constructed `host_addr`. If okay, then convert it to a string, assign it to `self.site`, and return Ok.
-        if let Ok(val) = Url::parse(&host_addr) {
-            self.site = Arc::new(val.as_str().to_string());
-            Ok("Successfully parsed given url.")
-
-        // Url could not be parsed. Return an error.
-        } else {
-            Err("Unable to parse provided url. Please check your `-u`/`--url` argument.")
-        }
+    /// If the provided url cannot be parsed, or isn't an http or https url, then it cannot be
+    /// scanned, so we have to return an error.
+    pub fn try_add_site(&mut self, provided_url: &str) -> Result<&str, String> {
+        // `Target` adds "http://" if no scheme is given, and keeps the port, path and query.
+        let target = Target::parse(provided_url)?;
+        self.site = Arc::new(target.as_str().to_string());
+        Ok("Successfully parsed given url.")
     }
 
-    /// This method wraps the `extend_from_file` method for the Wordlist struct. The sole argument is a
-    /// String that represents an absolute file path. This method then returns a Scanner object with an
-    /// initialized wordlist.
-    pub fn build_wordlist_from_file(&mut self, path: String) -> Self {
-        Scanner {
-            wordlist: self.wordlist.extend_from_file(path),
-            status_code_ignore_list: self.status_code_ignore_list.clone(),
-            found: self.found.clone(),
-            site: self.site.clone(),
-            extension_list: self.extension_list.clone(),
+    /// Attempt to add a url containing the `FUZZ` keyword to the `site` data member for a `fuzz`
+    /// scan. The url is stored exactly as it was given, since parsing it would change the keyword
+    /// (host names are lowercased), but it has to parse once the keyword is replaced by a word.
+    pub fn try_add_fuzz_template(&mut self, template: &str) -> Result<(), String> {
+        if !template.contains(FUZZ_KEYWORD) {
+            return Err(format!(
+                "The url needs to contain the `{}` keyword for a fuzz scan.",
+                FUZZ_KEYWORD
+            ));
         }
+
+        // Use "http://" when no scheme is given, just like `try_add_site`.
+        let template = with_scheme(template);
+
+        Target::parse(&template.replace(FUZZ_KEYWORD, "rustenum"))?;
+        self.site = Arc::new(template);
+        Ok(())
+    }
+
+    /// Adds the words of the wordlist file at `path` to the scanner's wordlist, read the way
+    /// `Wordlist::from_file` reads it. Returns an error if the file can't be read, so a bad path
+    /// never ends the process the scanner runs in.
+    pub fn build_wordlist_from_file(&mut self, path: &str) -> Result<(), String> {
+        self.wordlist.merge(Wordlist::from_file(path)?);
+        Ok(())
     }
 
     /// This method sorts the results by HTTP status code in ascending order. Then the results are formatted, given a color to represent
     /// their response type and then are printed to the screen.
     pub fn display_found(&self) {
-        // Convert the BTreeMap into a Vec which can then be easily sorted by value.
-        let mut sorted_results = Vec::from_iter(&self.found);
+        // Sort the results by status_code in ascending order.
+        let sorted_results = self.found.sorted();
+        self.display_header();
 
-        // sort the vector by status_code in ascending order.
-        sorted_results.sort_by(|a, b| a.1.cmp(b.1));
+        // Print all stored, sorted results. Display the resulting line with a different color
+        // depending on its associated http status_code.
+        let latency = self.latency_summary();
+        for result in sorted_results {
+            // Pages are displayed with a leading '/', other modes display the name as it is.
+            // Pages that were dramatically slower than the rest of the site are marked, and so
+            // are pages whose body matched a signature.
+            let mut page_display = self.result_display(result);
+            if let (Some(latency), Some(total_ms)) = (&latency, result.details.total_ms) {
+                if latency.is_slow(total_ms) {
+                    page_display += &format!("   [SLOW: {:.0} ms]", total_ms);
+                }
+            }
+            if !result.details.findings.is_empty() {
+                page_display += &format!("   [FOUND: {}]", finding_names(&result.details.findings));
+            }
+            println!("{}", result_line(&page_display, result.status));
+        }
+    }
 
+    /// Prints the same header as `display_found`, followed by the number of results with each
+    /// status code instead of the results themselves, since every hit was already printed the
+    /// moment it arrived.
+    pub fn display_summary(&self) {
+        self.display_header();
+        let counts: Vec<String> = self
+            .found
+            .status_counts()
+            .iter()
+            .map(|(status, count)| format!("{} x {}", count, status))
+            .collect();
+        match counts.is_empty() {
+            true => println!("No results"),
+            false => println!("{} results: {}", self.found.len(), counts.join(", ")),
+        }
+    }
+
+    /// Prints the site, method, mode and ignored status codes of the scan between two rules.
+    fn display_header(&self) {
         // Print a nice QOL message to show the results section.
         println!("---------------------------------------------------------");
         println!("Scan Results");
         println!("Site: {}", &self.site);
-        println!("Method: GET");
+        println!("Method: {}", self.method_name());
+        if self.mode != ScanMode::Dir {
+            println!("Mode: {:?}", self.mode);
+        }
         self.display_ignore_list();
         println!("---------------------------------------------------------");
+    }
 
-        // Print all stored, sorted results. Display the resulting line with a different color
-        // depending on its associated http status_code.
-        for (page, status_code) in sorted_results {
-            // Match status_codes based on 'type'
-            match status_code {
-                // Okay -> Page found codes. Green to indicate success.
-                200 => {
-                    let output = format!("/{} --> Status: {}", page, status_code).green();
-                    println!("{}", output);
-                }
-
-                // Redirects. Blue to indicate these are something to look into further.
-                300..=399 => {
-                    let output = format!("/{} --> Status: {}", page, status_code).blue();
-                    println!("{}", output);
-                }
+    /// Returns the HTTP method the scan sends its requests with. Only `params` scans can send
+    /// something other than GET.
+    pub fn method_name(&self) -> &'static str {
+        match self.mode {
+            ScanMode::Params => self.param_method.name(),
+            _ => "GET",
+        }
+    }
 
-                // Client & Server errors. Red to indicate that they are not immediately useful to the user.
-                400..=599 => {
-                    let output = format!("/{} --> Status: {}", page, status_code).red();
-                    println!("{}", output);
-                }
+    /// Summarizes the response times of every response received during the scan. Returns None if
+    /// no responses have been received.
+    pub fn latency_summary(&self) -> Option<LatencySummary> {
+        LatencySummary::from_samples(
+            &self.latencies,
+            self.slow_factor.unwrap_or(DEFAULT_SLOW_FACTOR),
+        )
+    }
 
-                // All other responses. Unlikely to see these codes, but this is a catch all.
-                // Yellow indicates caution or that these are 'unusual' codes to receive.
-                _ => {
-                    let output = format!("/{} --> Status: {}", page, status_code).yellow();
-                    println!("{}", output);
-                }
-            }
-        }
+    /// Returns the stored pages that were dramatically slower than the rest of the scan, slowest first.
+    pub fn slow_pages(&self) -> Vec<(&String, f64)> {
+        let latency = match self.latency_summary() {
+            Some(latency) => latency,
+            None => return Vec::new(),
+        };
+        let mut slow: Vec<(&String, f64)> = self
+            .found
+            .iter()
+            .filter_map(|result| {
+                result
+                    .details
+                    .total_ms
+                    .map(|total_ms| (&result.name, total_ms))
+            })
+            .filter(|(_, total_ms)| latency.is_slow(*total_ms))
+            .collect();
+        slow.sort_by(|a, b| b.1.total_cmp(&a.1));
+        slow
     }
 
     /// This method displays the stored list of HTTP status codes to ignore.
     pub fn display_ignore_list(&self) {
-        println!("Ignoring {:?}", self.status_code_ignore_list)
+        println!("Ignoring {}", self.status_code_ignore_list);
+        if !self.status_code_match_list.is_empty() {
+            println!("Matching {}", self.status_code_match_list);
+        }
     }
 
     /// This method adds the contents of to_use into the status_code_ignore_list. The
     /// to_use argument is a borrowed array slice of u16s.
     pub fn add_to_ignore_list(&mut self, to_use: &[u16]) {
         for status in to_use {
-            // If the status code already exists in the ignore_list then
-            // skip the current loop iteration.
-            if self.status_code_ignore_list.contains(status) {
-                continue;
-            }
-
-            // The status is doesn't currently exist in the status_code_ignore_list
-            // so add it.
-            self.status_code_ignore_list.push(*status);
+            // `add_code` skips any status code the ignore list already matches.
+            self.status_code_ignore_list.add_code(*status);
         }
     }
 
     /// Sets status_code_ignore_list to contain 404 which is the 'NOT FOUND' HTTP status code.
     pub fn use_default_ignore_list(&mut self) {
-        if !self.status_code_ignore_list.contains(&404) {
-            self.status_code_ignore_list.push(404);
-        }
+        self.status_code_ignore_list.add_code(404);
     }
 
-    /// Returns true if the 'found_status' argument is in the status_code_ignore_list
+    /// Returns true if the 'found_status' argument is matched by the status_code_ignore_list, or
+    /// isn't matched by a status_code_match_list that has rules in it.
     fn should_ignore(&self, found_status: &u16) -> bool {
-        self.status_code_ignore_list.contains(found_status)
+        is_ignored(
+            &self.status_code_ignore_list,
+            &self.status_code_match_list,
+            *found_status,
+        )
     }
 
-    /// Adds a page and received status code to the self.found: BTreeMap.
-    pub fn add_to_found(&mut self, (page, http_status): (String, u16)) {
-        // The http_status is not in our ignore list so add it to self.found
-        if !self.should_ignore(&http_status) {
-            self.found.insert(page, http_status);
+    /// Adds a result to self.found, unless its status code is ignored. A result for the same
+    /// request replaces the earlier one. Returns true if the result was stored.
+    pub fn add_to_found(&mut self, result: ScanResult) -> bool {
+        // The status is not in our ignore list so add it to self.found
+        if self.should_ignore(&result.status) {
+            return false;
+        }
+        self.found.insert(result);
+        true
+    }
+
+    /// Registers a plugin that is given every response of the scan, after the plugins that were
+    /// already registered.
+    pub fn add_plugin(&mut self, plugin: impl ResponsePlugin + 'static) {
+        self.plugins.add(Arc::new(plugin));
+    }
+
+    /// Returns how a result is displayed for the scanner's mode. A result requested with another
+    /// method than the rest of the scan has the method shown in front of it.
+    pub fn result_display(&self, result: &ScanResult) -> String {
+        let display = result.display_name(self.mode);
+        if result.method.is_empty() || result.method == self.method_name() {
+            display
+        } else {
+            format!("{} {}", result.method, display)
         }
     }
 
@@ -243,16 +759,17 @@ impl Scanner {
         let mut write_error = false;
 
         // If the file at `path` exists and is successfully opened in append mode.
-        if let Ok(mut file) = OpenOptions::new().append(true).open(&path) {
-            // Convert the BTreeMap into a Vec which can then be easily sorted by value.
-            let mut sorted_results = Vec::from_iter(&self.found);
-
-            // Sort the vector by status_code in ascending order.
-            sorted_results.sort_by(|a, b| a.1.cmp(b.1));
+        if let Ok(mut file) = OpenOptions::new().append(true).open(path) {
+            // Sort the results by status_code in ascending order.
+            let sorted_results = self.found.sorted();
 
             // Write each stored result as a byte string into the file.
-            for (path, status) in &sorted_results {
-                let byte_str = format!("/{} -> Status: {}\n", path, status);
+            for result in &sorted_results {
+                let byte_str = format!(
+                    "{} -> Status: {}\n",
+                    self.result_display(result),
+                    result.status
+                );
                 match file.write(byte_str.as_bytes()) {
                     // successfully wrote line, so continue to next iteration.
                     Ok(_val) => continue,
@@ -273,13 +790,17 @@ impl Scanner {
             }
         } else {
             // File doesn't exist, attempt to create a new one at `path`.
-            match File::create(&path) {
+            match File::create(path) {
                 // File was successfully created
                 Ok(mut file) => {
                     // For each stored result, convert it into a formatted byte string
                     // and write it to the file.
-                    for (path, status) in &self.found {
-                        let byte_str = format!("/{} -> Status: {}\n", path, status);
+                    for result in &self.found {
+                        let byte_str = format!(
+                            "{} -> Status: {}\n",
+                            self.result_display(result),
+                            result.status
+                        );
                         match file.write(byte_str.as_bytes()) {
                             // successfully wrote line, so continue to next iteration.
                             Ok(_val) => continue,
@@ -306,6 +827,147 @@ impl Scanner {
         }
     }
 
+    /// Opens the `live_output` file for appending, creating it if it doesn't exist. Returns None
+    /// if no file is set, or if it can't be opened, in which case the error is printed above the
+    /// `progress_bar`.
+    pub fn open_live_output(&self, progress_bar: &ProgressBar) -> Option<File> {
+        let path = self.live_output.as_ref()?;
+        match OpenOptions::new().append(true).create(true).open(path) {
+            Ok(file) => Some(file),
+            Err(_err) => {
+                progress_bar.println(
+                    "Could not create output file at provided path. Please check your file path.",
+                );
+                None
+            }
+        }
+    }
+
+    /// Reports a hit stored in `found` while the scan is still running. The hit is printed above
+    /// the `progress_bar`, added to the dashboard if there is one, sent to `hits` if it is set,
+    /// and written to the `live_output` file, if there is one, in the same format as
+    /// `write_results_to_file`. The file is closed after the first failed write.
+    pub fn report_hit(
+        &self,
+        result: &ScanResult,
+        progress_bar: &ProgressBar,
+        live_output: &mut Option<File>,
+    ) {
+        let mut page_display = self.result_display(result);
+        if !result.details.findings.is_empty() {
+            page_display += &format!("   [FOUND: {}]", finding_names(&result.details.findings));
+        }
+        progress_bar.println(result_line(&page_display, result.status).to_string());
+        if let Some(control) = &self.control {
+            control.record_hit(&page_display, result.status, result.size());
+        }
+        if let Some(hits) = &self.hits {
+            // Nobody is listening anymore if the receiver is gone, which isn't a problem here.
+            let _ = hits.send(result.clone());
+        }
+
+        if let Some(file) = live_output {
+            let line = format!(
+                "{} -> Status: {}\n",
+                self.result_display(result),
+                result.status
+            );
+            if file.write_all(line.as_bytes()).is_err() {
+                let path = self.live_output.clone().unwrap_or_default();
+                progress_bar.println("Couldn't write results to ".to_string() + &path);
+                *live_output = None;
+            }
+        }
+    }
+
+    /// Checks whether a request for `url` is in scope and within the request budget. A request that
+    /// may be sent is counted against the budget. A request that may not is stored in `skipped`
+    /// and the reason is returned.
+    pub fn check_scope(&mut self, url: &str) -> Result<(), SkipReason> {
+        let path = Url::parse(url)
+            .map(|url| url.path().to_string())
+            .unwrap_or_else(|_| url.to_string());
+        let mut result = self.scope.check(&path);
+        if let (Ok(()), Some(max)) = (&result, self.scope.max_requests) {
+            if self.sent_requests >= max {
+                result = Err(SkipReason::Budget);
+            }
+        }
+        match &result {
+            Ok(()) => self.sent_requests += 1,
+            Err(reason) => self.skipped.push(SkippedRequest {
+                url: url.to_string(),
+                reason: reason.clone(),
+            }),
+        }
+        result
+    }
+
+    /// Returns the probes that are in scope and within the request budget, in the same order. The
+    /// others are stored in `skipped`.
+    pub fn scoped_probes(&mut self, probes: Vec<Probe>) -> Vec<Probe> {
+        probes
+            .into_iter()
+            .filter(|probe| self.check_scope(&probe.url).is_ok())
+            .collect()
+    }
+
+    /// Writes every skipped candidate to the file at `path`, one per line as the reason followed
+    /// by a tab and the url. The file is replaced if it exists.
+    pub fn write_skipped(&self, path: &str) -> Result<String, String> {
+        let mut contents = String::new();
+        for skipped in &self.skipped {
+            contents += &format!("{}\t{}\n", skipped.reason, skipped.url);
+        }
+        match std::fs::write(path, contents) {
+            Ok(_) => Ok("Successfully wrote skipped requests to ".to_string() + path),
+            Err(_err) => Err("Couldn't write skipped requests to ".to_string() + path),
+        }
+    }
+
+    /// Writes every word that never got a response to the file at `path`, one word per line, so
+    /// the file can be given to `-w`/`--wordlist` to scan just those words again. The file is
+    /// replaced if it exists.
+    pub fn write_unreachable(&self, path: &str) -> Result<String, String> {
+        let mut words = self.unreachable.clone();
+        words.sort();
+        words.dedup();
+
+        let mut contents = String::new();
+        for word in &words {
+            contents += word;
+            contents += "\n";
+        }
+        match std::fs::write(path, contents) {
+            Ok(_) => Ok("Successfully wrote unreachable words to ".to_string() + path),
+            Err(_err) => Err("Couldn't write unreachable words to ".to_string() + path),
+        }
+    }
+
+    /// Writes the index of the saved responses to `index.json` in `dir`, sorted by url, so every
+    /// url can be looked up to find the file its response was saved to. The file is replaced if
+    /// it exists.
+    pub fn write_response_index(&self, dir: &str) -> Result<String, String> {
+        let mut saved = self.saved_responses.clone();
+        saved.sort_by(|a, b| (&a.url, &a.host).cmp(&(&b.url, &b.host)));
+
+        let path = std::path::Path::new(dir).join(INDEX_FILE);
+        let path = path.to_string_lossy().to_string();
+        let contents = serde_json::to_string_pretty(&saved)
+            .map_err(|_err| "Couldn't write the response index to ".to_string() + &path)?;
+        if std::fs::create_dir_all(dir).is_err() {
+            return Err("Couldn't write the response index to ".to_string() + &path);
+        }
+        match std::fs::write(&path, contents) {
+            Ok(_) => Ok(format!(
+                "Successfully saved {} responses to {}",
+                saved.len(),
+                dir
+            )),
+            Err(_err) => Err("Couldn't write the response index to ".to_string() + &path),
+        }
+    }
+
     /// This method either:
     /// 1) displays every stored extension that is being searched for
     /// 2) or displays a message stating that none are searched for.
@@ -2,95 +2,1105 @@
 //!
 //! Tristan Gomez - Winter 2022
 
-use futures::{stream, StreamExt};
+use clap::error::ErrorKind;
+use clap::{Args, CommandFactory, Parser, Subcommand};
+use clap_complete::Shell;
 use indicatif::{ProgressBar, ProgressStyle};
-use reqwest::Client;
+use regex::Regex;
+use rustenum::bench::{self, DEFAULT_BENCH_REQUESTS};
+use rustenum::config::{ConfigFile, HttpVersion, ScanConfig, MAX_THREADS};
+use rustenum::dashboard::{Dashboard, ScanControl};
+use rustenum::diff::ScanDiff;
+use rustenum::distributed::{serve_worker, DEFAULT_WORKER_ADDRESS};
+use rustenum::dns;
+use rustenum::graphql::DEFAULT_SCHEMA_DIR;
+use rustenum::params::ParamMethod;
+use rustenum::report::ScanReport;
+use rustenum::scan::ScanMode;
+use rustenum::services::{probe_services, DEFAULT_PROBE_TIMEOUT_SECS};
+use rustenum::target::Target;
+use rustenum::wordlist::chunk_path;
 use rustenum::*;
-use std::collections::HashMap;
 use std::env;
-use std::sync::atomic::AtomicUsize;
-use std::time::Instant;
+use std::io;
+use std::sync::Arc;
+use std::time::{Duration, Instant, SystemTime};
+
+const EXAMPLES: &str = "\
+EXAMPLES:
+  rustenum dir -w /usr/share/wordlists/common.txt -t 4 -u http://10.10.10.10
+  rustenum dir -w /usr/share/wordlists/common.txt -x .php,html,js -u http://example.com
+  rustenum vhost -w subdomains.txt -u http://10.10.10.10 -d example.com
+  rustenum dns -w subdomains.txt -d example.com
+  rustenum fuzz -w params.txt -u 'http://example.com/index.php?FUZZ=1'
+  rustenum params -w params.txt -X POST -u http://example.com/login.php
+  rustenum -c rustenum.toml -p php dir -u http://example.com
+  rustenum report results.json --html report.html
+  rustenum diff before.json after.json --json changes.json
+  rustenum bench --http-version http2 -t 14
+  rustenum worker --listen 0.0.0.0:7878
+  rustenum wordlist common.txt extra.txt --dedup --max-length 30 -o merged.txt
+  rustenum wordlist --from-results a.json --from-results b.json --sort-frequency -o hits.txt
+  rustenum dir -w big.txt --targets hosts.txt --workers 10.0.0.5:7878,10.0.0.6:7878
+  rustenum dir -w common.txt --bypass -u http://example.com
+  rustenum dir -w common.txt --openapi -u http://api.example.com
+  rustenum dir -w common.txt --graphql -u http://api.example.com
+Remember to use the correct HTTP scheme (HTTP/HTTPS) for the --url argument.";
+
+/// RustEnum - A website enumeration tool!
+#[derive(Debug, Parser)]
+#[command(name = "rustenum", version, after_help = EXAMPLES)]
+struct Cli {
+    /// TOML config file to load default settings from. Command-line flags override the config file
+    #[arg(short, long, global = true, value_name = "FILE")]
+    config: Option<String>,
+
+    /// Name of a profile in the config file to apply on top of its defaults
+    #[arg(short, long, global = true, value_name = "NAME")]
+    profile: Option<String>,
+
+    #[command(subcommand)]
+    command: Command,
+}
+
+#[derive(Debug, Subcommand)]
+enum Command {
+    /// Enumerate pages and directories by appending each word to the url
+    Dir(DirArgs),
+
+    /// Enumerate virtual hosts by sending each word as a subdomain in the `Host` header
+    Vhost(VhostArgs),
+
+    /// Enumerate subdomains by looking up each word as a subdomain over DNS
+    Dns(DnsArgs),
+
+    /// Replace the FUZZ keyword anywhere in the url with each word
+    Fuzz(UrlArgs),
+
+    /// Discover hidden GET or POST parameters of the url by sending each word as a parameter name
+    Params(ParamsArgs),
+
+    /// Build an HTML report from a saved JSON results file. No scan is run
+    Report(ReportArgs),
+
+    /// Compare two saved JSON results files and show new, removed and changed pages
+    Diff(DiffArgs),
+
+    /// Measure the requests per second of the scan engine against a local test server
+    Bench(BenchArgs),
+
+    /// Merge, clean, sort, filter and split wordlists, or build one from saved results
+    Wordlist(WordlistArgs),
+
+    /// Scan the shards of requests handed out by a coordinator scan run with --workers
+    Worker(WorkerArgs),
+
+    /// Print a shell completion script to stdout
+    Completions {
+        /// The shell to generate the script for
+        shell: Shell,
+    },
+}
+
+/// Settings shared by every HTTP scan mode.
+#[derive(Debug, Args)]
+struct ScanArgs {
+    /// Wordlist file for the scan. Can be given more than once
+    #[arg(short, long = "wordlist", value_name = "FILE")]
+    wordlists: Vec<String>,
+
+    /// Number of concurrent requests [default: 10]
+    #[arg(short, long, value_name = "COUNT", value_parser = clap::value_parser!(u64).range(1..=MAX_THREADS as u64))]
+    threads: Option<u64>,
+
+    /// Comma separated list of file extensions to append to each word
+    #[arg(short = 'x', long, value_name = "EXTENSIONS", value_delimiter = ',')]
+    extensions: Vec<String>,
+
+    /// Comma separated list of HTTP status codes to ignore. Codes can be ranges like 400-499,
+    /// classes like 4xx, or negated like !403 to keep a code a range would ignore. 404 is always
+    /// ignored
+    #[arg(short, long, value_name = "CODES", value_parser = StatusMatcher::parse)]
+    ignore: Option<StatusMatcher>,
+
+    /// Comma separated list of HTTP status codes to keep, with the same syntax as --ignore. Every
+    /// other status code is ignored
+    #[arg(long, value_name = "CODES", value_parser = StatusMatcher::parse)]
+    match_status: Option<StatusMatcher>,
+
+    /// Header to send with every request, formatted as 'Name: value'. Can be given more than once
+    #[arg(short = 'H', long = "header", value_name = "HEADER", value_parser = parse_header)]
+    headers: Vec<(String, String)>,
+
+    /// File to write the results to. Results are appended if the file exists
+    #[arg(short, long, value_name = "FILE")]
+    output: Option<String>,
+
+    /// File to write the results to as JSON
+    #[arg(long, value_name = "FILE")]
+    json: Option<String>,
+
+    /// File to write an HTML report to
+    #[arg(long, value_name = "FILE")]
+    html: Option<String>,
+
+    /// Flag pages that are this many times slower than the median response [default: 5]
+    #[arg(long, value_name = "FACTOR")]
+    slow_factor: Option<f64>,
+
+    /// Number of times a request is sent before its word is given up on [default: 3]
+    #[arg(long, value_name = "COUNT", value_parser = clap::value_parser!(u32).range(1..))]
+    max_attempts: Option<u32>,
+
+    /// Milliseconds to wait before the first retry. The wait doubles after every attempt [default: 250]
+    #[arg(long, value_name = "MS")]
+    backoff: Option<u64>,
+
+    /// Comma separated list of HTTP status codes to retry [default: 502,503,504]
+    #[arg(long, value_name = "CODES", value_delimiter = ',', value_parser = clap::value_parser!(u16).range(100..=599))]
+    retry_status: Vec<u16>,
+
+    /// Seconds to wait for a connection to the server
+    #[arg(long, value_name = "SECONDS")]
+    connect_timeout: Option<f64>,
+
+    /// Seconds to wait for a whole response, from sending the request until the body is read
+    #[arg(long, value_name = "SECONDS")]
+    timeout: Option<f64>,
+
+    /// File to write the words that never got a response to, for scanning them again later
+    #[arg(long, value_name = "FILE")]
+    unreachable: Option<String>,
+
+    /// Check every response body for stack traces, SQL errors, directory listings, keys, internal IPs and email addresses
+    #[arg(long)]
+    analyze: bool,
+
+    /// Signatures file to check every response body against instead of the built-in signatures
+    #[arg(long, value_name = "FILE")]
+    signatures: Option<String>,
+
+    /// Directory to save the raw response (status line, headers and body) of every matched result to
+    #[arg(long, value_name = "DIR")]
+    save_responses: Option<String>,
+
+    /// Only request paths matching this pattern. A glob like '/api/*', or a regex after 're:'. Can be given more than once
+    #[arg(long, value_name = "PATTERN")]
+    include: Vec<String>,
+
+    /// Never request paths matching this pattern. A glob like '*.pdf', or a regex after 're:'. Can be given more than once
+    #[arg(long, value_name = "PATTERN")]
+    exclude: Vec<String>,
+
+    /// Comma separated list of path segments that are never requested [default: logout,delete,...]
+    #[arg(long, value_name = "WORDS", value_delimiter = ',')]
+    dangerous: Vec<String>,
+
+    /// Request dangerous paths like /logout and /delete too
+    #[arg(long)]
+    allow_dangerous: bool,
+
+    /// Most requests the scan may send. Retries aren't counted
+    #[arg(long, value_name = "COUNT", value_parser = clap::value_parser!(u64).range(1..))]
+    max_requests: Option<u64>,
+
+    /// File to write the candidate requests that were out of scope to, with the reason for each
+    #[arg(long, value_name = "FILE")]
+    skipped: Option<String>,
+
+    /// File to append a line of JSON to for every request sent, with the time, method, url, a hash of the headers, the status and the size
+    #[arg(long, value_name = "FILE")]
+    audit_log: Option<String>,
+
+    /// Show an interactive dashboard instead of the progress bar. Keys pause the scan, change the thread count and ignore status codes
+    #[arg(long)]
+    dashboard: bool,
+
+    /// List every result again, sorted by status code, once the scan is done. Hits are always printed as they arrive
+    #[arg(long)]
+    list_results: bool,
+
+    /// Command of a program to give every response to as a plugin, one line of JSON at a time. Can be given more than once
+    #[arg(long = "plugin", value_name = "COMMAND")]
+    plugins: Vec<String>,
+
+    /// Retry every page that answered 401 or 403 with header, path and method variations that get past common access rules
+    #[arg(long)]
+    bypass: bool,
+
+    /// Comma separated list of `rustenum worker` addresses, like 10.0.0.5:7878, to share the requests of the scan between
+    #[arg(long, value_name = "ADDRESSES", value_delimiter = ',')]
+    workers: Vec<String>,
+
+    /// Number of requests in each shard handed to a worker [default: 500]
+    #[arg(long, value_name = "COUNT", value_parser = clap::value_parser!(u64).range(1..))]
+    shard_size: Option<u64>,
+
+    #[command(flatten)]
+    connection: ConnectionArgs,
+}
+
+/// Settings for how the HTTP client connects to the server.
+#[derive(Debug, Args)]
+struct ConnectionArgs {
+    /// HTTP version to send requests with: auto, http1 or http2. http2 uses prior knowledge, so it also works for http:// urls (h2c)
+    #[arg(long, value_name = "VERSION", value_parser = HttpVersion::parse)]
+    http_version: Option<HttpVersion>,
+
+    /// Most idle connections kept open per host for reuse. 0 opens a new connection for every request
+    #[arg(long, value_name = "COUNT")]
+    pool_size: Option<usize>,
+}
+
+/// Arguments for the scan modes that only need a url.
+#[derive(Debug, Args)]
+struct UrlArgs {
+    /// The address of the host to scan. 'http://' is used if no scheme is given
+    #[arg(short, long)]
+    url: Option<String>,
+
+    #[command(flatten)]
+    scan: ScanArgs,
+}
+
+#[derive(Debug, Args)]
+struct DirArgs {
+    /// The address of the host to scan. 'http://' is used if no scheme is given
+    #[arg(short, long)]
+    url: Option<String>,
+
+    /// Probe the host of the url on http:80, https:443, http:8080 and https:8443, and scan every service that answers
+    #[arg(long)]
+    probe_services: bool,
+
+    /// Comma separated list of services to probe instead, like https:8443, or a port on its own for both http and https
+    #[arg(long, value_name = "SERVICES", value_delimiter = ',')]
+    services: Vec<String>,
+
+    /// File of urls to scan, one per line, along with the --url
+    #[arg(long, value_name = "FILE")]
+    targets: Option<String>,
+
+    /// Look for an OpenAPI/Swagger description at common paths like /swagger.json, and request every documented endpoint
+    #[arg(long)]
+    openapi: bool,
+
+    /// Url or file of an OpenAPI/Swagger description (JSON or YAML) whose endpoints are requested. Can be given more than once
+    #[arg(long = "openapi-spec", value_name = "LOCATION")]
+    openapi_specs: Vec<String>,
+
+    /// Check the results at common GraphQL paths like /graphql for GraphQL endpoints, and save the schema of those with introspection enabled
+    #[arg(long)]
+    graphql: bool,
+
+    /// Directory the schemas of GraphQL endpoints are saved to [default: graphql-schemas]
+    #[arg(long, value_name = "DIR")]
+    graphql_schemas: Option<String>,
+
+    #[command(flatten)]
+    scan: ScanArgs,
+}
+
+#[derive(Debug, Args)]
+struct VhostArgs {
+    /// The address of the host to scan. 'http://' is used if no scheme is given
+    #[arg(short, long)]
+    url: Option<String>,
+
+    /// The domain each word is prepended to. Defaults to the host of the url
+    #[arg(short, long)]
+    domain: Option<String>,
+
+    #[command(flatten)]
+    scan: ScanArgs,
+}
+
+#[derive(Debug, Args)]
+struct ParamsArgs {
+    /// The address of the endpoint to scan. 'http://' is used if no scheme is given
+    #[arg(short, long)]
+    url: Option<String>,
+
+    /// Send the parameters in the query string (GET) or in a form body (POST) [default: GET]
+    #[arg(short = 'X', long, value_name = "METHOD", value_parser = ParamMethod::parse)]
+    method: Option<ParamMethod>,
+
+    /// Number of parameter names sent in each request [default: 32]
+    #[arg(short, long, value_name = "COUNT", value_parser = clap::value_parser!(u64).range(1..))]
+    batch_size: Option<u64>,
+
+    #[command(flatten)]
+    scan: ScanArgs,
+}
+
+#[derive(Debug, Args)]
+struct DnsArgs {
+    /// The domain to enumerate subdomains of
+    #[arg(short, long)]
+    domain: String,
+
+    /// Wordlist file of subdomains. Can be given more than once
+    #[arg(short, long = "wordlist", value_name = "FILE")]
+    wordlists: Vec<String>,
+
+    /// Number of concurrent lookups [default: 10]
+    #[arg(short, long, value_name = "COUNT", value_parser = clap::value_parser!(u64).range(1..=MAX_THREADS as u64))]
+    threads: Option<u64>,
+
+    /// File to write the results to
+    #[arg(short, long, value_name = "FILE")]
+    output: Option<String>,
+
+    /// File to write the results to as JSON
+    #[arg(long, value_name = "FILE")]
+    json: Option<String>,
+}
+
+#[derive(Debug, Args)]
+struct ReportArgs {
+    /// JSON results file from a previous scan
+    results: String,
+
+    /// File to write the HTML report to
+    #[arg(long, value_name = "FILE")]
+    html: String,
+}
+
+#[derive(Debug, Args)]
+struct BenchArgs {
+    /// Number of requests to send
+    #[arg(short = 'n', long, value_name = "COUNT", default_value_t = DEFAULT_BENCH_REQUESTS)]
+    requests: usize,
+
+    /// Number of concurrent requests [default: 10]
+    #[arg(short, long, value_name = "COUNT", value_parser = clap::value_parser!(u64).range(1..=MAX_THREADS as u64))]
+    threads: Option<u64>,
+
+    /// Exit with an error if fewer requests per second than this were answered
+    #[arg(long, value_name = "RATE")]
+    min_rps: Option<f64>,
+
+    #[command(flatten)]
+    connection: ConnectionArgs,
+}
+
+#[derive(Debug, Args)]
+struct WordlistArgs {
+    /// Wordlist files to merge, in order
+    wordlists: Vec<String>,
+
+    /// JSON results file of a previous dir scan whose pages are added as words. Can be given more than once
+    #[arg(long = "from-results", value_name = "FILE")]
+    results: Vec<String>,
+
+    /// Only keep the words matching this regular expression
+    #[arg(long = "match", value_name = "REGEX")]
+    match_pattern: Option<String>,
+
+    /// Leave out the words matching this regular expression
+    #[arg(long = "exclude", value_name = "REGEX")]
+    exclude_pattern: Option<String>,
+
+    /// Leave out the words shorter than this many characters
+    #[arg(long, value_name = "LENGTH")]
+    min_length: Option<usize>,
+
+    /// Leave out the words longer than this many characters
+    #[arg(long, value_name = "LENGTH")]
+    max_length: Option<usize>,
+
+    /// Leave out repeated words, keeping the first time each word appears
+    #[arg(long)]
+    dedup: bool,
+
+    /// Sort the words by how often they appear, most common first. Repeats are left out
+    #[arg(long)]
+    sort_frequency: bool,
+
+    /// Split the words into files of this many words each, numbered after the --output file
+    #[arg(long, value_name = "COUNT", value_parser = clap::value_parser!(u64).range(1..))]
+    split: Option<u64>,
+
+    /// File to write the words to. The words are printed when no file is given
+    #[arg(short, long, value_name = "FILE")]
+    output: Option<String>,
+}
+
+#[derive(Debug, Args)]
+struct WorkerArgs {
+    /// The address to listen for coordinators on. Use 0.0.0.0:<port> to take jobs from other hosts
+    #[arg(short, long, value_name = "ADDRESS", default_value = DEFAULT_WORKER_ADDRESS)]
+    listen: String,
+
+    /// File on this host to append a line of JSON to for every request the worker sends
+    #[arg(long, value_name = "FILE")]
+    audit_log: Option<String>,
+}
+
+#[derive(Debug, Args)]
+struct DiffArgs {
+    /// JSON results file from the older scan
+    old: String,
+
+    /// JSON results file from the newer scan
+    new: String,
+
+    /// File to write the differences to as JSON
+    #[arg(long, value_name = "FILE")]
+    json: Option<String>,
+}
+
+impl ScanArgs {
+    /// Converts the scan flags into a `ScanConfig` for the target `url`.
+    fn into_config(self, url: Option<String>) -> ScanConfig {
+        ScanConfig {
+            url,
+            wordlists: self.wordlists,
+            extensions: self.extensions,
+            ignore: self.ignore.unwrap_or_default(),
+            match_status: self.match_status.unwrap_or_default(),
+            headers: self.headers.into_iter().collect(),
+            threads: self.threads.map(|threads| threads as usize),
+            output: self.output,
+            json: self.json,
+            html: self.html,
+            slow_factor: self.slow_factor,
+            max_attempts: self.max_attempts,
+            backoff_ms: self.backoff,
+            retry_statuses: self.retry_status,
+            connect_timeout: self.connect_timeout,
+            timeout: self.timeout,
+            unreachable: self.unreachable,
+            http_version: self.connection.http_version,
+            pool_size: self.connection.pool_size,
+            param_method: None,
+            batch_size: None,
+            analyze: self.analyze.then_some(true),
+            signatures: self.signatures,
+            save_responses: self.save_responses,
+            dashboard: self.dashboard.then_some(true),
+            list_results: self.list_results.then_some(true),
+            include: self.include,
+            exclude: self.exclude,
+            dangerous: self.dangerous,
+            allow_dangerous: self.allow_dangerous.then_some(true),
+            max_requests: self.max_requests.map(|max| max as usize),
+            skipped: self.skipped,
+            audit_log: self.audit_log,
+            probe_services: None,
+            services: Vec::new(),
+            targets: None,
+            workers: self.workers,
+            shard_size: self.shard_size.map(|shard_size| shard_size as usize),
+            plugins: self.plugins,
+            bypass: self.bypass.then_some(true),
+            openapi: None,
+            openapi_specs: Vec::new(),
+            graphql: None,
+            graphql_schemas: None,
+        }
+    }
+}
 
 #[tokio::main]
 async fn main() {
+    // Translate the flags from older versions of RustEnum before clap parses the arguments.
+    let cli = Cli::parse_from(legacy_arguments(env::args().collect()));
+
+    match cli.command {
+        Command::Dir(args) => {
+            let overrides = ScanConfig {
+                probe_services: args.probe_services.then_some(true),
+                services: args.services,
+                targets: args.targets,
+                openapi: args.openapi.then_some(true),
+                openapi_specs: args.openapi_specs,
+                graphql: args.graphql.then_some(true),
+                graphql_schemas: args.graphql_schemas,
+                ..args.scan.into_config(args.url)
+            };
+            let config = load_config(&cli.config, &cli.profile, overrides);
+            run_scan(config, ScanMode::Dir, "").await;
+        }
+        Command::Fuzz(args) => {
+            let config = load_config(&cli.config, &cli.profile, args.scan.into_config(args.url));
+            run_scan(config, ScanMode::Fuzz, "").await;
+        }
+        Command::Params(args) => {
+            let overrides = ScanConfig {
+                param_method: args.method,
+                batch_size: args.batch_size.map(|batch_size| batch_size as usize),
+                ..args.scan.into_config(args.url)
+            };
+            let config = load_config(&cli.config, &cli.profile, overrides);
+            run_scan(config, ScanMode::Params, "").await;
+        }
+        Command::Vhost(args) => {
+            let config = load_config(&cli.config, &cli.profile, args.scan.into_config(args.url));
+
+            // Use the host of the url as the domain if none was given.
+            let domain = match args.domain {
+                Some(domain) => domain,
+                None => config
+                    .url
+                    .as_deref()
+                    .and_then(|url| Target::parse(url).ok())
+                    .and_then(|target| target.url().host_str().map(|host| host.to_string()))
+                    .unwrap_or_else(|| {
+                        invalid_arguments(
+                            ErrorKind::MissingRequiredArgument,
+                            "A --domain is required when the url doesn't have a host name.",
+                        )
+                    }),
+            };
+            run_scan(config, ScanMode::Vhost, &domain).await;
+        }
+        Command::Dns(args) => {
+            let overrides = ScanConfig {
+                wordlists: args.wordlists,
+                threads: args.threads.map(|threads| threads as usize),
+                output: args.output,
+                json: args.json,
+                ..Default::default()
+            };
+            let config = load_config(&cli.config, &cli.profile, overrides);
+            run_dns(config, &args.domain).await;
+        }
+        Command::Bench(args) => {
+            let overrides = ScanConfig {
+                threads: args.threads.map(|threads| threads as usize),
+                http_version: args.connection.http_version,
+                pool_size: args.connection.pool_size,
+                ..Default::default()
+            };
+            let config = load_config(&cli.config, &cli.profile, overrides);
+            run_bench(config, args.requests, args.min_rps).await;
+        }
+        Command::Wordlist(args) => run_wordlist(args),
+        Command::Worker(args) => run_worker(&args.listen, args.audit_log).await,
+        Command::Report(args) => build_report_from_file(&args.results, &args.html),
+        Command::Diff(args) => run_diff(&args.old, &args.new, args.json.as_deref()),
+        Command::Completions { shell } => {
+            clap_complete::generate(shell, &mut Cli::command(), "rustenum", &mut io::stdout());
+        }
+    }
+}
+
+/// This method runs a `dir`, `vhost`, `fuzz` or `params` scan with the settings in `config`. The
+/// `domain` is only used by `vhost` scans. A `dir` scan that probes for services is run against
+/// every service that answers.
+async fn run_scan(config: ScanConfig, mode: ScanMode, domain: &str) {
+    // Make sure there is enough information to run a scan.
+    if let Err(err) = config.validate() {
+        invalid_arguments(ErrorKind::MissingRequiredArgument, &err);
+    }
+    if config.probes_services() && mode != ScanMode::Dir {
+        invalid_arguments(
+            ErrorKind::ArgumentConflict,
+            "Services can only be probed for a dir scan.",
+        );
+    }
+    if config.targets.is_some() && (mode != ScanMode::Dir || config.probes_services()) {
+        invalid_arguments(
+            ErrorKind::ArgumentConflict,
+            "A targets file can only be used by a dir scan that doesn't probe services.",
+        );
+    }
+    if config.uses_openapi() && (mode != ScanMode::Dir || config.targets.is_some()) {
+        invalid_arguments(
+            ErrorKind::ArgumentConflict,
+            "API descriptions can only be read by a dir scan of a single url.",
+        );
+    }
+    if config.uses_graphql() && mode != ScanMode::Dir {
+        invalid_arguments(
+            ErrorKind::ArgumentConflict,
+            "GraphQL endpoints can only be looked for by a dir scan.",
+        );
+    }
+    if !config.plugins.is_empty() && mode == ScanMode::Params {
+        invalid_arguments(
+            ErrorKind::ArgumentConflict,
+            "Plugins can't be run by a params scan.",
+        );
+    }
+    let shows_dashboard = config.dashboard == Some(true);
+    if !config.workers.is_empty()
+        && (mode == ScanMode::Params
+            || shows_dashboard
+            || config.save_responses.is_some()
+            || !config.plugins.is_empty())
+    {
+        invalid_arguments(
+            ErrorKind::ArgumentConflict,
+            "Workers can't run params scans, show a dashboard, save responses or run plugins.",
+        );
+    }
+
+    display_banner();
+
+    match config.probes_services() {
+        true => scan_services(config).await,
+        false => scan_target(config, mode, domain).await,
+    }
+}
+
+/// This method probes the host of the url in `config` on every configured service, and runs a
+/// `dir` scan against each service that answers, one after the other. Every service gets its own
+/// section of results and its own output files.
+async fn scan_services(config: ScanConfig) {
+    let target = Target::parse(config.url.as_deref().unwrap_or_default())
+        .unwrap_or_else(|err| invalid_arguments(ErrorKind::ValueValidation, &err));
+    let services = config
+        .service_list()
+        .unwrap_or_else(|err| invalid_arguments(ErrorKind::ValueValidation, &err));
+    let client = config
+        .build_client()
+        .unwrap_or_else(|err| invalid_arguments(ErrorKind::ValueValidation, &err));
+    let audit = config
+        .audit_log()
+        .unwrap_or_else(|err| invalid_arguments(ErrorKind::ValueValidation, &err));
+
+    println!(
+        "Probing {} services on {}",
+        services.len(),
+        target.url().host_str().unwrap_or_default()
+    );
+    let timeout = config.timeout.unwrap_or(DEFAULT_PROBE_TIMEOUT_SECS);
+    let live = probe_services(
+        &client,
+        &target,
+        &services,
+        Duration::from_secs_f64(timeout),
+        audit.as_ref(),
+    )
+    .await;
+    if live.is_empty() {
+        error("None of the services answered.");
+    }
+    for service in &live {
+        let server = match &service.server {
+            Some(server) => format!(" ({})", server),
+            None => String::new(),
+        };
+        println!("{} --> Status: {}{}", service.url, service.status, server);
+    }
+
+    for service in &live {
+        println!("---------------------------------------------------------");
+        println!("Scanning {} at {}", service.service, service.url);
+        scan_target(config.for_service(service), ScanMode::Dir, "").await;
+    }
+}
+
+/// This method runs a scan of the url in `config` that has already been validated.
+async fn scan_target(config: ScanConfig, mode: ScanMode, domain: &str) {
     // Initialize a new reqwest::Client object that will eventually send
     // GET requests. Reqwest recommends using a Client object over the 'reqwest::get()'
     // method when making large numbers of GET requests.
-    let client = Client::builder()
-        // Set the client to never follow redirects.
-        .redirect(reqwest::redirect::Policy::none())
-        .build()
-        .unwrap();
-
-    // skip past the name of the program in argv and collect the rest of the command-line args.
-    let cmd_args: Vec<String> = env::args().skip(1).collect();
-
-    // process the command-line arguments and store them in a `processed_args` hashmap.
-    let processed_args = process_arguments(&cmd_args);
-
-    // Create a new scanner object.
-    let mut scanner = Scanner::new();
-
-    // Check if website exists and can be correctly parsed.
-    if processed_args.contains_key("-u") {
-        match scanner.try_add_site(processed_args.get("-u").unwrap()) {
-            // Url successfully parsed, do nothing but continue program execution.
-            Ok(_msg) => {}
-
-            // Error when parsing url, we cannot continue program execution.
-            // Provide diagnostic message to user, call usage() and exit.
-            Err(err) => {
-                eprintln!("{}", err);
-                usage();
-            }
+    let client = config
+        .build_client()
+        .unwrap_or_else(|err| invalid_arguments(ErrorKind::ValueValidation, &err));
+
+    // Create a new scanner object from the configuration. This checks that the website can be
+    // correctly parsed, loads the wordlists, and sets up the ignore list and extensions.
+    let mut scanner = Scanner::from_config(&config, mode)
+        .unwrap_or_else(|err| invalid_arguments(ErrorKind::ValueValidation, &err));
+
+    // The number of concurrent/parallel requests that can be sent. The default is 10.
+    let thread_count = config.thread_count();
+
+    // Every word in the wordlist becomes one request, for every target if there are several.
+    let probes = match config.targets {
+        Some(_) => config
+            .target_urls()
+            .and_then(|targets| scanner.target_probes(&targets))
+            .unwrap_or_else(|err| invalid_arguments(ErrorKind::ValueValidation, &err)),
+        None => scanner.probes(domain),
+    };
+
+    // The documented endpoints of an API description are requested along with the wordlist.
+    let mut probes = probes;
+    if config.uses_openapi() {
+        let specs = scanner
+            .load_api_specs(&client, &config.openapi_specs)
+            .await
+            .unwrap_or_else(|err| error(&err));
+        for spec in &specs {
+            println!(
+                "Found an API description at {} with {} endpoints",
+                spec.source,
+                spec.operations.len()
+            );
         }
+        let send_unsafe = config.allow_dangerous == Some(true);
+        let api_probes = scanner
+            .api_probes(&specs, send_unsafe, &probes)
+            .unwrap_or_else(|err| error(&err));
+        probes.extend(api_probes);
     }
 
-    // Valid site, next step is to check if wordlist is valid.
-    // If it is then load its contents into the Scanner object.
-    let mut infile = String::new();
+    // We need to initialize a progress bar with a length that is the number of requests to send.
+    // The dashboard shows the progress itself, so the progress bar is hidden when it is used.
+    let use_dashboard = config.dashboard == Some(true);
+    if use_dashboard && mode == ScanMode::Params {
+        invalid_arguments(
+            ErrorKind::ArgumentConflict,
+            "The dashboard can't be used with a params scan.",
+        );
+    }
+    let progress_bar = match use_dashboard {
+        true => ProgressBar::hidden(),
+        false => new_progress_bar(probes.len() as u64),
+    };
 
-    // User provided wordlist flag and argument. Store the file name into `infile`.
-    if processed_args.contains_key("-w") {
-        if let Some(val) = processed_args.get("-w") {
-            infile += val;
-        }
+    println!("Starting Scan.");
+    if !config.workers.is_empty() {
+        println!("Sharing the scan between {} workers", config.workers.len());
+    }
+
+    // Scan is beginning take the time to be used later to determine how long
+    // the scan took.
+    let now = Instant::now();
+    let started = SystemTime::now();
 
-    // User did *not* provide a wordlist flag and argument. Program execution cannot
-    // continue. Call usage() and exit.
+    // Parameter discovery decides which requests to send as the responses arrive, the other
+    // modes send every probe once.
+    if mode == ScanMode::Params {
+        if let Err(err) = scanner
+            .discover_params(&client, thread_count, &progress_bar)
+            .await
+        {
+            progress_bar.finish_and_clear();
+            error(&err);
+        }
+    } else if !config.workers.is_empty() {
+        if let Err(err) = scanner
+            .run_distributed(&config, probes, &progress_bar)
+            .await
+        {
+            progress_bar.finish_and_clear();
+            error(&err);
+        }
+    } else if use_dashboard {
+        let control = Arc::new(ScanControl::new(
+            &scanner.site,
+            thread_count,
+            probes.len(),
+            &scanner.status_code_ignore_list,
+        ));
+        scanner.control = Some(control.clone());
+        let dashboard = Dashboard::start(control).unwrap_or_else(|err| error(&err));
+        scanner
+            .run(&client, probes, thread_count, &progress_bar)
+            .await;
+        dashboard.stop().await;
     } else {
-        usage();
+        scanner
+            .run(&client, probes, thread_count, &progress_bar)
+            .await;
+    }
+
+    // Scan is over, finish and clear the progress bar.
+    progress_bar.finish_and_clear();
+
+    // The results of the documented endpoints show which ones answer without credentials.
+    if !scanner.api_endpoints.is_empty() {
+        let reachable = scanner.check_api_endpoints();
+        println!(
+            "{} of {} API endpoints answered without authentication",
+            reachable,
+            scanner.api_endpoints.len()
+        );
+    }
+
+    // Forbidden pages are tried again with the bypass variants once every page is known.
+    if config.bypass == Some(true) {
+        let headers = config
+            .header_map()
+            .unwrap_or_else(|err| invalid_arguments(ErrorKind::ValueValidation, &err));
+        let timeout = config.timeout.unwrap_or(DEFAULT_PROBE_TIMEOUT_SECS);
+        let progress_bar = new_progress_bar(0);
+        let bypasses = scanner
+            .probe_bypasses(
+                &headers,
+                Duration::from_secs_f64(timeout),
+                thread_count,
+                config.allow_dangerous == Some(true),
+                &progress_bar,
+            )
+            .await;
+        progress_bar.finish_and_clear();
+        println!("Found {} bypasses of forbidden pages", bypasses);
+    }
+
+    // The results at common GraphQL paths are queried to see which ones are GraphQL endpoints.
+    if config.uses_graphql() {
+        let schema_dir = config
+            .graphql_schemas
+            .as_deref()
+            .unwrap_or(DEFAULT_SCHEMA_DIR);
+        let progress_bar = new_progress_bar(0);
+        let endpoints = scanner
+            .probe_graphql(&client, schema_dir, thread_count, &progress_bar)
+            .await;
+        progress_bar.finish_and_clear();
+        let introspection = scanner
+            .graphql_endpoints
+            .iter()
+            .filter(|endpoint| endpoint.introspection)
+            .count();
+        println!(
+            "Found {} GraphQL endpoints, {} with introspection enabled",
+            endpoints, introspection
+        );
+    }
+
+    println!("Scan Complete");
+    println!("Time elapsed: {} seconds", now.elapsed().as_secs());
+    if let Some(latency) = scanner.latency_summary() {
+        println!("{}", latency.display_line());
+    }
+
+    // Hits were printed as they arrived, except on the dashboard, so they are only listed again
+    // when asked for.
+    match config.list_results == Some(true) || use_dashboard {
+        true => scanner.display_found(),
+        false => scanner.display_summary(),
+    }
+    if !scanner.unreachable.is_empty() {
+        eprintln!(
+            "{} words never got a response after {} attempts.",
+            scanner.unreachable.len(),
+            scanner.retry_policy.max_attempts
+        );
+    }
+
+    // The structured output formats are all built from the same report of the scan.
+    let report = ScanReport::new(&scanner, thread_count, started, now.elapsed());
+
+    // If the user gave the option to write the results as JSON then attempt to do so.
+    if let Some(path) = &config.json {
+        match report.write_json(path) {
+            Ok(msg) => println!("{}", &msg),
+            Err(err) => eprintln!("{}", &err),
+        }
+    }
+
+    // If the user gave the option to write an HTML report then attempt to do so.
+    if let Some(path) = &config.html {
+        match report.write_html(path) {
+            Ok(msg) => println!("{}", &msg),
+            Err(err) => eprintln!("{}", &err),
+        }
+    }
+
+    // Entries that couldn't be written leave a gap in the audit log, so the user needs to know.
+    if let Some(audit) = &scanner.audit {
+        if audit.failures() > 0 {
+            eprintln!(
+                "{} requests couldn't be written to the audit log {}.",
+                audit.failures(),
+                audit.path()
+            );
+        }
+    }
+
+    // Show how many candidates were out of scope, and write them out if the user asked for them.
+    if !scanner.skipped.is_empty() {
+        eprintln!(
+            "{} requests were skipped as out of scope.",
+            scanner.skipped.len()
+        );
+    }
+    if let Some(path) = &config.skipped {
+        match scanner.write_skipped(path) {
+            Ok(msg) => println!("{}", &msg),
+            Err(err) => eprintln!("{}", &err),
+        }
+    }
+
+    // If the user gave the option to keep the words that never got a response then write them
+    // out, so they can be given to `--wordlist` in a later scan.
+    if let Some(path) = &config.unreachable {
+        match scanner.write_unreachable(path) {
+            Ok(msg) => println!("{}", &msg),
+            Err(err) => eprintln!("{}", &err),
+        }
+    }
+
+    // If the user gave the option to save the matched responses then write the index that maps
+    // every url to the file its response was saved to.
+    if let Some(dir) = &config.save_responses {
+        match scanner.write_response_index(dir) {
+            Ok(msg) => println!("{}", &msg),
+            Err(err) => eprintln!("{}", &err),
+        }
+    }
+
+    // If the user gave the option to write the results to a file, every hit was already
+    // appended to it the moment it arrived.
+    if let Some(path) = &config.output {
+        println!("Wrote results to {} as they were found", path);
+    }
+}
+
+/// This method runs a `dns` scan of `domain` with the wordlists and thread count in `config`.
+async fn run_dns(config: ScanConfig, domain: &str) {
+    if let Err(err) = config.validate_wordlists_and_threads() {
+        invalid_arguments(ErrorKind::MissingRequiredArgument, &err);
+    }
+
+    let mut wordlist = Wordlist::new();
+    for path in &config.wordlists {
+        wordlist.extend_from_file(path.clone());
+    }
+
+    display_banner();
+    let progress_bar = new_progress_bar(wordlist.len() as u64);
+    println!("Starting Scan.");
+    let now = Instant::now();
+
+    let results =
+        dns::resolve_subdomains(domain, &wordlist, config.thread_count(), &progress_bar).await;
+
+    progress_bar.finish_and_clear();
+    println!("Scan Complete");
+    println!("Time elapsed: {} seconds", now.elapsed().as_secs());
+    results.display();
+
+    if let Some(path) = &config.json {
+        match results.write_json(path) {
+            Ok(msg) => println!("{}", &msg),
+            Err(err) => eprintln!("{}", &err),
+        }
+    }
+    if let Some(path) = &config.output {
+        match results.write_results_to_file(path) {
+            Ok(msg) => println!("{}", &msg),
+            Err(err) => eprintln!("{}", &err),
+        }
     }
+}
+
+/// This method runs the `bench` subcommand. It sends `requests` requests through the scan engine
+/// to a local server with the client settings in `config`, and exits with an error if fewer than
+/// `min_rps` requests per second were answered.
+async fn run_bench(config: ScanConfig, requests: usize, min_rps: Option<f64>) {
+    display_banner();
+    println!("Starting Benchmark.");
+
+    let result = bench::run_benchmark(&config, requests)
+        .await
+        .unwrap_or_else(|err| error(&err));
+    result.display();
 
-    scanner.build_wordlist_from_file(infile);
+    if let Some(min_rps) = min_rps {
+        if result.requests_per_sec < min_rps {
+            error(&format!(
+                "Throughput of {:.0} requests/sec is below the minimum of {:.0}.",
+                result.requests_per_sec, min_rps
+            ));
+        }
+    }
+}
 
-    // Add "404" to the ignore list by default.
-    scanner.use_default_ignore_list();
+/// This method runs the `wordlist` subcommand. The wordlists and the pages of the results files
+/// are merged, filtered, cleaned of repeats or sorted, and written to the output file, split into
+/// chunks, or printed.
+fn run_wordlist(args: WordlistArgs) {
+    if args.wordlists.is_empty() && args.results.is_empty() {
+        invalid_arguments(
+            ErrorKind::MissingRequiredArgument,
+            "No wordlists or results files were given.",
+        );
+    }
+    if args.split.is_some() && args.output.is_none() {
+        invalid_arguments(
+            ErrorKind::MissingRequiredArgument,
+            "The --split flag requires an --output file to number the chunks after.",
+        );
+    }
+    let pattern = |source: &Option<String>| {
+        source.as_deref().map(|source| {
+            Regex::new(source).unwrap_or_else(|err| {
+                invalid_arguments(
+                    ErrorKind::ValueValidation,
+                    &format!("Invalid regex `{}`: {}", source, err),
+                )
+            })
+        })
+    };
+    let (match_pattern, exclude_pattern) =
+        (pattern(&args.match_pattern), pattern(&args.exclude_pattern));
 
-    // The user provided the flag for adding a list of file extensions to each webpage in the scan.
-    if processed_args.contains_key("-x") {
-        if let Some(val) = processed_args.get("-x") {
-            // process the provided list of extensions and add them to the scanner.
-            scanner.add_extensions_to_wordlist(val);
+    let mut wordlist = Wordlist::new();
+    for path in &args.wordlists {
+        wordlist.merge(Wordlist::from_file(path).unwrap_or_else(|err| error(&err)));
+    }
+    wordlist.merge(Wordlist::from_results(&args.results).unwrap_or_else(|err| error(&err)));
+
+    if let Some(pattern) = &match_pattern {
+        wordlist.retain_matching(pattern);
+    }
+    if let Some(pattern) = &exclude_pattern {
+        wordlist.remove_matching(pattern);
+    }
+    wordlist.retain_lengths(args.min_length, args.max_length);
+    if args.sort_frequency {
+        wordlist.sort_by_frequency();
+    } else if args.dedup {
+        wordlist.dedup();
+    }
+
+    match (&args.output, args.split) {
+        (Some(path), Some(size)) => {
+            for (index, chunk) in wordlist.chunks(size as usize).iter().enumerate() {
+                match chunk.write_to_file(&chunk_path(path, index + 1)) {
+                    Ok(msg) => println!("{}", &msg),
+                    Err(err) => error(&err),
+                }
+            }
+        }
+        (Some(path), None) => match wordlist.write_to_file(path) {
+            Ok(msg) => println!("{}", &msg),
+            Err(err) => error(&err),
+        },
+        (None, _) => {
+            for word in &wordlist.0 {
+                println!("{}", word);
+            }
         }
     }
+}
+
+/// This method runs the `worker` subcommand. It listens on `address` and scans the shards every
+/// coordinator that connects hands to it, until the process is stopped.
+async fn run_worker(address: &str, audit_log: Option<String>) {
+    let listener = tokio::net::TcpListener::bind(address)
+        .await
+        .unwrap_or_else(|err| error(&format!("Could not listen on {}: {}", address, err)));
+    let address = listener
+        .local_addr()
+        .map(|address| address.to_string())
+        .unwrap_or_else(|_| address.to_string());
+    println!("Listening for jobs on {}", address);
+    if let Err(err) = serve_worker(listener, audit_log).await {
+        error(&err);
+    }
+}
 
-    // Print a nice message to the user.
+/// Print a nice message to the user.
+fn display_banner() {
     println!("---------------------------------------------------------");
     println!("\u{1f50e} RustEnum - A webpage enumeration tool \u{1f50D}");
     println!("By Tristan Gomez");
     println!("---------------------------------------------------------");
+}
 
-    // This variable is used to keep track of how many webpages have been enumerated.
-    // It is an AtomicUsize because it needs to be safely shared between threads.
-    let mut words_used = AtomicUsize::new(0);
-
-    // This variable represents the total number of pages needed to be enumerated.
-    // It is cast from a usize to a u64 for use in the progress_bar object.
-    let wordlist_size = scanner.wordlist.len() as u64;
-
-    // We need to initialize a progress bar with a length that is the number of pages to enumerate through.
-    let progress_bar = ProgressBar::new(wordlist_size);
+/// This method makes the progress bar displayed during a scan of `len` requests.
+fn new_progress_bar(len: u64) -> ProgressBar {
+    let progress_bar = ProgressBar::new(len);
 
     // This code in `progress_bar.set_style()` is almost exactly taken from the `indicatif` documentation/examples.
     // Please see the README for extra information, including a link to the code where this segment was taken from.
@@ -106,288 +1116,157 @@ async fn main() {
             // This sets the progress bar to fill with '#' as it progresses, with the leading character being a '>'.
             .progress_chars("#>-"),
     );
+    progress_bar
+}
 
-    // The user has designated more HTTP status codes to ignore. We will process them
-    // and add them to the scanner's ignore list.
-    if processed_args.contains_key("-ig") {
-        let ignore_list = parse_ignore(processed_args.get("-ig").unwrap());
-        scanner.add_to_ignore_list(&ignore_list);
-    }
+/// Displays a clap style error for an argument problem that could only be found after parsing,
+/// like a missing url that wasn't in the config file either, and exits.
+fn invalid_arguments(kind: ErrorKind, message: &str) -> ! {
+    Cli::command().error(kind, message).exit()
+}
 
-    // The default number of concurrent/parallel requests that can be sent is 10.
-    let mut thread_count = 10_usize;
+/// Older versions of RustEnum had no subcommands and used multi-character short flags. This method
+/// translates those arguments so they still work: `-ig`, `-oj` and `-oh` become `--ignore`, `--json`
+/// and `--html`, and `dir` is used when no subcommand is given.
+fn legacy_arguments(mut cmd_args: Vec<String>) -> Vec<String> {
+    let mut cli = Cli::command();
+    cli.build();
+    let dir = cli
+        .find_subcommand("dir")
+        .expect("the dir subcommand exists");
 
-    // If the user wants to change the default number of concurrent/parallel requests being sent.
-    if processed_args.contains_key("-t") {
-        thread_count = processed_args
-            // get the desired thread count. Attempt to parse it as a usize. If the parse is successful
-            // then assign the number to thread_count. It not, display a diagnostic message, call usage()
-            // and exit.
-            .get("-t")
-            .unwrap()
-            .parse::<usize>()
-            .unwrap_or_else(|err| {
-                eprintln!("Error ocurred when attempting to get thread count: {}", err);
-                usage();
-            });
-
-        // To prevent DoS (malicious or accidental), I am capping the number of concurrent/parallel
-        // requests to 14.
-        if thread_count > 14 {
-            println!("Cannot specify a thread count higher than 14.");
-            usage();
-        } else if thread_count < 1 {
-            println!(
-                "The minimum number of threads needs to be in the range of 1 to 14 inclusive."
-            );
-            usage();
-        }
+    // A subcommand can only be the first argument that isn't an option or the value of one. The
+    // options in front of it are global ones, which `dir` has too, or those of a legacy call.
+    let mut position = 1;
+    while position < cmd_args.len() && cmd_args[position].starts_with('-') {
+        position += match takes_value(dir, &legacy_flag(&cmd_args[position])) {
+            true => 2,
+            false => 1,
+        };
     }
+    let subcommand = cmd_args.get(position).map(|arg| arg.as_str());
+    let has_subcommand =
+        subcommand.is_some_and(|arg| arg == "help" || cli.find_subcommand(arg).is_some());
+    let asks_for_help = matches!(
+        cmd_args.get(1).map(|arg| arg.as_str()),
+        None | Some("-h") | Some("--help") | Some("-V") | Some("--version")
+    );
 
-    println!("Starting Scan.");
-
-    // Scan is beginning take the time to be used later to determine how long
-    // the scan took.
-    let now = Instant::now();
-
-    // The code immediately below assigning the Vec<(String, u16)> to `temp` is mostly
-    // taken from a Stack Overflow post by user Shepmaster. The url to the post/solution
-    // is in the README.
-
-    // I am creating an iterator over the scanner's wordlist then turning it into a stream iterator.
-    let temp: Vec<(String, u16)> = stream::iter(scanner.wordlist.0.iter())
-        // Each word in the stream iterator is then mapped to the block of code that scans a web page.
-        .map(|word| {
-            // We need to take a reference to the client object so we can use it to send GET requests.
-            let client = &client;
-
-            // We need to get the main url/site path from the scanner object.
-            let path = scanner.site.clone();
-
-            // Increment the words_used AtomicUsize variable to progress the
-            // progress bar.
-            let words_used = &mut words_used;
-            *words_used.get_mut() += 1;
-
-            // update the progress_bar with the new count of words_used.
-            progress_bar.set_position(*words_used.get_mut() as u64);
-
-            async move {
-                // construct the full url by concatenating the url_path + "/" + word_from_wordlist
-                let path = &path;
-                let mut url;
-                if path.ends_with('/') {
-                    url = path.to_string();
-                    url += word;
-                } else {
-                    url = path.to_string();
-                    url += "/";
-                    url += word;
-                }
-
-                // Send the get request for the url and await a response.
-                match client.get(url).send().await {
-                    // This isn't the best solution because it does very "bad" error handling.
-                    // It is explained in the comments for both 'process_response' and 'failed_response()'.
-                    // Both methods return a (String, u16), but the 'failed_response' method
-                    // returns ("failed", 404) as a work around.
-                    // 'process_response' returns the results of the request.
-                    // In this program, the 404 status code from a failed request will prevent the tuple from being
-                    // added to the results list.
-                    Ok(resp) => process_response(word.to_string(), resp),
-                    Err(_) => failed_response(),
-                }
-            }
-        })
-        // buffer_unordered caps the number of parallel/concurrent GET requests being sent to the value of 'thread_count'/
-        // This should prevent DoS for a website by preventing every request from being sent at once. The default limit is
-        // 10 requests at a time, but it can be changed to any value from 1 ..=14.
-        // I am using buffer_unordered because I do not care about the order of my GET responses. I only care that they arrived.
-        // When a response is received, then a new GET request is added to the buffer_unordered queue.
-        .buffer_unordered(thread_count)
-        // Await all responses and collect the responses at the end of the scan.
-        .collect()
-        .await;
-
-    // For every result found, process them.
-    // `add_to_found()` ignores any tuple that has
-    // a u16 in the scanner's ignore list.
-    for result in temp {
-        scanner.add_to_found(result);
+    // Legacy flags are only translated where a flag can be, never in the value of an option.
+    let command = match has_subcommand {
+        true => subcommand.and_then(|arg| cli.find_subcommand(arg)),
+        false => Some(dir),
+    };
+    let mut is_value = false;
+    for arg in cmd_args.iter_mut().skip(1) {
+        if is_value {
+            is_value = false;
+            continue;
+        }
+        *arg = legacy_flag(arg);
+        is_value = command.is_some_and(|command| takes_value(command, arg));
     }
 
-    // Scan is over, finish and clear the progress bar.
-    progress_bar.finish_and_clear();
-
-    println!("Scan Complete");
-    println!("Time elapsed: {} seconds", now.elapsed().as_secs());
-    scanner.display_found();
+    if !has_subcommand && !asks_for_help {
+        cmd_args.insert(1, "dir".to_string());
+    }
+    cmd_args
+}
 
-    // if the user gave the option to write the results to a file then attempt to do so.
-    if processed_args.contains_key("-o") {
-        // Get the file path for the outfile and attempt to write to it. This will
-        // attempt to create a new file if the one at the provided path doesn't exist.
-        match scanner.write_results_to_file(processed_args.get("-o").unwrap()) {
-            Ok(msg) => println!("{}", &msg),
-            Err(err) => {
-                eprintln!("{}", &err);
-            }
-        }
+/// Returns the flag the multi-character short flag `arg` of older versions became, or `arg` as it
+/// is if it isn't one.
+fn legacy_flag(arg: &str) -> String {
+    match arg {
+        "-ig" => "--ignore",
+        "-oj" => "--json",
+        "-oh" => "--html",
+        _ => arg,
     }
+    .to_string()
 }
 
-/// This method displays the manual of flags used in the program. This method exits the program without an error upon finishing.
-fn display_man_page() -> ! {
-    println!("RustEnum - A website enumeration tool!");
-    println!("by Tristan Gomez - Winter 2022 - Intro to Rust Programming\n");
-    println!("[REQUIRED FLAG(S)]");
-    println!("  -w ,  --wordlist      Provide website page wordlist for scan.");
-    println!("  -u ,  --url           The address of the host to scan. If 'http://' or 'https://' is not prepended to the url then 'http://' is used by default.");
-    println!("[Options]");
-    println!("  -ig , --ignore        Provide a comma separated list of HTTP status codes to ignore. Default without this option is 404. If flag is set, 404 is still by default included in the ignore list.");
-    println!(
-        "  -o ,  --output        Provide a file name/path for the scan to write its results to."
-    );
-    println!("  -t ,  --threads       Provide a thread count for number of concurrent requests. Default is 10, Max is 14.");
-    println!("  -x ,  --extensions    Provide a comma separated of file extensions to append to each word in the wordlist.");
-    std::process::exit(0);
-}
-
-/// This displays some examples on how to use this program. It also points the user to the use the -h/--help flag to
-/// get more help with using this program.
-fn usage() -> ! {
-    println!("./rustenum [optional flags] -w /usr/share/wordlist/dirb/common.txt [optional flags] -u [host]");
-    println!("Remember to use the correct HTTP scheme (HTTP/HTTPS) for the --url argument.");
-    println!("EXAMPLE: ./rustenum -w /usr/share/wordlists/common.txt -t 4 -u http://10.10.10.10");
-    println!("EXAMPLE: ./rustenum -w /usr/share/wordlists/common.txt -u https://10.10.10.10");
-    println!("EXAMPLE: ./rustenum -w /usr/share/wordlists/common.txt -u http://example.com");
-    println!("EXAMPLE: ./rustenum -w /usr/share/wordlists/common.txt -x .php,html,js -u http://example.com");
-    println!("Use the '-h' or '--help' flags for man page.");
-    std::process::exit(1);
-}
-
-/// This method processes the command-line arguments for the program.
-/// It takes a slice of an array of Strings as its argument and after
-/// processing the arguments will return them formatted in a HashMap.
-fn process_arguments(cmd_args: &[String]) -> HashMap<String, String> {
-    let mut cmd_options: HashMap<String, String> = HashMap::new();
-    match cmd_args.len() {
-        // No flags given. This is incorrect, call `usage()` and exit.
-        0 => usage(),
-
-        // 1 argument/flag given. Check if it is -h/--help.
-        1 => {
-            // if argument is -h or --help then display man page and exit.
-            if cmd_args[0] == *"-h" || cmd_args[0] == "--help" {
-                display_man_page();
-
-            // some incorrect value was provided so call `usage` and exit.
-            } else {
-                usage();
+/// Returns true if `flag`, like "-w" or "--wordlist", is an option of `command` whose value is the
+/// next argument. Flags with their value attached, like "--ignore=404", don't take the next one.
+fn takes_value(command: &clap::Command, flag: &str) -> bool {
+    if flag.contains('=') {
+        return false;
+    }
+    command.get_arguments().any(|arg| {
+        let named = match flag.strip_prefix("--") {
+            Some(long) => arg.get_long() == Some(long),
+            None => {
+                let mut short = flag.chars().skip(1);
+                match (short.next(), short.next()) {
+                    (Some(short), None) => arg.get_short() == Some(short),
+                    _ => false,
+                }
             }
-        }
+        };
+        named && arg.get_action().takes_values()
+    })
+}
 
-        // Multiple command-line arguments were provided, process them.
-        _ => {
-            let mut i: usize = 0;
-            while i < cmd_args.len() {
-                if cmd_args[i] == "-w" || cmd_args[i] == "--wordlist" {
-                    cmd_options.insert("-w".to_string(), cmd_args[i + 1].clone());
-                    i += 2;
-                } else if cmd_args[i] == "-u" || cmd_args[i] == "--url" {
-                    cmd_options.insert("-u".to_string(), cmd_args[i + 1].clone());
-                    i += 2;
-                } else if cmd_args[i] == "-ig" || cmd_args[i] == "--ignore" {
-                    cmd_options.insert("-ig".to_string(), cmd_args[i + 1].clone());
-                    i += 2;
-                } else if cmd_args[i] == "-o" || cmd_args[i] == "--output" {
-                    cmd_options.insert("-o".to_string(), cmd_args[i + 1].clone());
-                    i += 2;
-                } else if cmd_args[i] == "-t" || cmd_args[i] == "--threads" {
-                    cmd_options.insert("-t".to_string(), cmd_args[i + 1].clone());
-                    i += 2;
-                } else if cmd_args[i] == "-x" || cmd_args[i] == "--extensions" {
-                    cmd_options.insert("-x".to_string(), cmd_args[i + 1].clone());
-                    i += 2;
-                } else {
-                    usage();
-                }
+/// This method builds the settings for the scan. If a config file was given then its defaults and
+/// the chosen profile are loaded first, and the command-line arguments are applied on top of them.
+fn load_config(
+    config_file: &Option<String>,
+    profile: &Option<String>,
+    overrides: ScanConfig,
+) -> ScanConfig {
+    match config_file {
+        Some(path) => ConfigFile::from_file(path)
+            .and_then(|file| file.resolve(profile.as_deref()))
+            .unwrap_or_else(|err| error(&err))
+            .merge(overrides),
+
+        // A profile can only come from a config file.
+        None => {
+            if profile.is_some() {
+                invalid_arguments(
+                    ErrorKind::MissingRequiredArgument,
+                    "The --profile flag requires a --config file.",
+                );
             }
-            cmd_options
+            overrides
         }
     }
 }
 
-/// This method takes a string slice as its argument and returns a vector of u16s.
-/// The argument should be a comma-separated list of http status codes to ignore.
-fn parse_ignore(ignore_args: &str) -> Vec<u16> {
-    let mut codes_to_use: Vec<u16> = Vec::new();
+/// This method builds an HTML report from the JSON results file at `json_path` saved by a
+/// previous scan, and writes it to `html_path`.
+fn build_report_from_file(json_path: &str, html_path: &str) {
+    let report = ScanReport::from_json_file(json_path).unwrap_or_else(|err| error(&err));
 
-    // Split the string slice at the commas and for each word, attempt to add them to the
-    // codes_to_use vector.
-    for status_code in ignore_args.split(',') {
-        // Try to parse status code as u16. If this fails then call `usage()` and exit program.
-        codes_to_use.push(status_code.parse().unwrap_or_else(|err| {
-            eprintln!("{}", err);
-            usage();
-        }));
+    match report.write_html(html_path) {
+        Ok(msg) => println!("{}", &msg),
+        Err(err) => error(&err),
     }
-    codes_to_use
 }
 
-/// This method processes a reqwest::Response object received from the Client.get() in the scan.
-/// This method takes 'ext'(webpage that was requested) and 'resp' as arguments.
-pub fn process_response(ext: String, resp: reqwest::Response) -> (String, u16) {
-    // If the response is a 301 redirect
-    if resp.status().as_u16() == 301 {
-        // If the location header was provided in the response object then
-        // we need to format the returned object to reflect that. Attempt to
-        // get the location header's value.
-        if let Ok(location) = resp
-            .headers()
-            .get(reqwest::header::LOCATION)
-            .unwrap()
-            .to_str()
-        {
-            // display the requested page, the page the request
-            // should be redirected to, and the http status code.
-            (
-                ext + "   [REDIRECTED TO: " + location + "]",
-                u16::from(resp.status()),
-            )
-
-        // Location header doesn't exist. Return (page enumerated, http status code)
-        } else {
-            (ext, u16::from(resp.status()))
-        }
+/// This method runs the `diff` subcommand. It compares the results file at `old_path` with the
+/// one at `new_path`, displays the differences, and writes them as JSON to `json_path` if given.
+fn run_diff(old_path: &str, new_path: &str, json_path: Option<&str>) {
+    let old_report = ScanReport::from_json_file(old_path).unwrap_or_else(|err| error(&err));
+    let new_report = ScanReport::from_json_file(new_path).unwrap_or_else(|err| error(&err));
 
-    // If the response was a 302 redirect.
-    } else if resp.status().as_u16() == 302 {
-        // If the location header was provided in the response object then
-        // we need to format the returned object to reflect that. Attempt to
-        // get the location header's value.
-        if let Ok(location) = resp
-            .headers()
-            .get(reqwest::header::LOCATION)
-            .unwrap()
-            .to_str()
-        {
-            // display the requested page, the page the request
-            // should be redirected to, and the http status code.
-            (ext + "   [" + location + "]", u16::from(resp.status()))
+    let diff = ScanDiff::between(&old_report, &new_report);
+    diff.display();
 
-        // Location header doesn't exist. Return (page enumerated, http status code)
-        } else {
-            (ext, u16::from(resp.status()))
+    if let Some(path) = json_path {
+        match diff.write_json(path) {
+            Ok(msg) => println!("{}", &msg),
+            Err(err) => error(&err),
         }
-    } else {
-        // Normal case. Return the page requested and its http response.
-        (ext, u16::from(resp.status()))
     }
 }
 
-/// Generic failed response. Returns a 404 status code and a placeholder value of "failed".
-/// Since the status code is 404, it will be ignored by the scanner in this program.
-pub fn failed_response() -> (String, u16) {
-    ("failed".to_string(), 404u16)
+/// This method takes a string slice formatted as "Name: value" and splits it into the
+/// header name and value. It is used by clap to validate the `--header` flag.
+fn parse_header(header_arg: &str) -> Result<(String, String), String> {
+    match header_arg.split_once(':') {
+        Some((name, value)) if !name.trim().is_empty() => {
+            Ok((name.trim().to_string(), value.trim().to_string()))
+        }
+        _ => Err("headers need to be formatted as 'Name: value'".to_string()),
+    }
 }
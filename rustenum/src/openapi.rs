@@ -0,0 +1,506 @@
+//! Enumerating the endpoints of an API from its OpenAPI or Swagger description.
+//!
+//! Many APIs publish a description of every endpoint they have, at paths like `/swagger.json` or
+//! `/openapi.yaml`. A `dir` scan can look for one at the common paths, or be given where it is,
+//! and every documented path and method is added to the scan next to the words of the wordlist.
+//! Path parameters are filled with the example, default or first allowed value from the
+//! description, or with a sample value for their type, and so are required query parameters.
+//!
+//! Both OpenAPI 3 and Swagger 2.0 are read, as JSON or as YAML. Only the base path of the server
+//! the description names is used: every endpoint is requested on the host being scanned, so a
+//! description that names another host never sends requests there. Methods that can change data,
+//! like POST and DELETE, are only sent when dangerous requests are allowed.
+
+use crate::audit::AuditLog;
+use crate::results::ResultKey;
+use crate::retry::RetryPolicy;
+use crate::scan::{send_probe, Probe};
+use crate::scope::{SkipReason, SkippedRequest};
+use crate::target::Target;
+use crate::Scanner;
+use reqwest::{Client, Url};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::HashSet;
+use std::fs;
+
+/// The paths an API description is looked for at when none is given.
+pub const COMMON_SPEC_PATHS: [&str; 10] = [
+    "swagger.json",
+    "swagger.yaml",
+    "openapi.json",
+    "openapi.yaml",
+    "openapi.yml",
+    "api-docs",
+    "v2/api-docs",
+    "v3/api-docs",
+    "swagger/v1/swagger.json",
+    "api/swagger.json",
+];
+
+/// The methods that don't change anything on the server, which are always sent.
+pub const SAFE_METHODS: [&str; 4] = ["GET", "HEAD", "OPTIONS", "TRACE"];
+
+/// The methods an operation can be documented under, as they are written in a description.
+const METHODS: [&str; 8] = [
+    "get", "put", "post", "delete", "options", "head", "patch", "trace",
+];
+
+/// How many `$ref`s are followed in a row before giving up, so a reference to itself can't loop.
+const MAX_REFS: usize = 16;
+
+/// A single documented operation: a method on a path.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ApiOperation {
+    pub method: String,
+
+    // The path as it is documented, with the base path in front of it, like "/v1/users/{id}".
+    pub path: String,
+
+    // The path with a sample value for every path parameter, like "/v1/users/1".
+    pub sample_path: String,
+
+    // A sample value for every required query parameter.
+    pub query: Vec<(String, String)>,
+
+    // Whether the description says the operation needs credentials.
+    pub secured: bool,
+}
+
+/// A parsed API description.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ApiSpec {
+    // Where the description was read from: a url or a file.
+    pub source: String,
+
+    // Every documented operation, sorted by path.
+    pub operations: Vec<ApiOperation>,
+}
+
+impl ApiSpec {
+    /// Parses an OpenAPI 3 or Swagger 2.0 description written as JSON or YAML, read from `source`.
+    /// Returns an error if the text can't be parsed, or if it isn't an API description.
+    pub fn parse(source: &str, text: &str) -> Result<Self, String> {
+        let document: Value = match text.trim_start().starts_with('{') {
+            true => serde_json::from_str(text).map_err(|err| err.to_string()),
+            false => serde_yaml::from_str(text).map_err(|err| err.to_string()),
+        }
+        .map_err(|err| format!("Could not parse the API description {}: {}", source, err))?;
+        if document.get("openapi").is_none() && document.get("swagger").is_none() {
+            return Err(format!(
+                "{} isn't an OpenAPI or Swagger description.",
+                source
+            ));
+        }
+        let paths = match document.get("paths").and_then(Value::as_object) {
+            Some(paths) => paths,
+            None => return Err(format!("{} doesn't document any paths.", source)),
+        };
+
+        let base_path = base_path(&document);
+        let secured_by_default = is_secured(document.get("security"));
+        let mut operations = Vec::new();
+        for (path, item) in paths {
+            let item = resolve(&document, item);
+            let shared = parameters(&document, item.get("parameters"));
+            for method in METHODS {
+                let operation = match item.get(method) {
+                    Some(operation) => resolve(&document, operation),
+                    None => continue,
+                };
+
+                // Parameters of the operation replace the ones of the path with the same name.
+                let mut params = parameters(&document, operation.get("parameters"));
+                for param in &shared {
+                    if !params
+                        .iter()
+                        .any(|other| other.name == param.name && other.location == param.location)
+                    {
+                        params.push(param.clone());
+                    }
+                }
+
+                let mut sample_path = path.clone();
+                let mut query = Vec::new();
+                for param in &params {
+                    match param.location.as_str() {
+                        "path" => {
+                            sample_path =
+                                sample_path.replace(&format!("{{{}}}", param.name), &param.sample)
+                        }
+                        "query" if param.required => {
+                            query.push((param.name.clone(), param.sample.clone()))
+                        }
+                        _ => {}
+                    }
+                }
+
+                // An operation's own `security`, even an empty one, replaces the default.
+                let secured = match operation.get("security") {
+                    Some(security) => is_secured(Some(security)),
+                    None => secured_by_default,
+                };
+                operations.push(ApiOperation {
+                    method: method.to_uppercase(),
+                    path: join_paths(&base_path, path),
+                    sample_path: join_paths(&base_path, &sample_path),
+                    query,
+                    secured,
+                });
+            }
+        }
+        Ok(ApiSpec {
+            source: source.to_string(),
+            operations,
+        })
+    }
+}
+
+/// A parameter of an operation, with the sample value it is sent with.
+#[derive(Debug, Clone)]
+struct Parameter {
+    name: String,
+
+    // Where the parameter goes: "path", "query", "header" or "cookie".
+    location: String,
+
+    required: bool,
+
+    sample: String,
+}
+
+/// Returns the parameters in a `parameters` list, with every `$ref` followed.
+fn parameters(document: &Value, list: Option<&Value>) -> Vec<Parameter> {
+    list.and_then(Value::as_array)
+        .map(|list| {
+            list.iter()
+                .map(|param| resolve(document, param))
+                .filter_map(|param| {
+                    Some(Parameter {
+                        name: param.get("name")?.as_str()?.to_string(),
+                        location: param.get("in")?.as_str()?.to_string(),
+                        required: param.get("required").and_then(Value::as_bool) == Some(true),
+                        sample: sample_value(document, param),
+                    })
+                })
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+/// Returns the value a parameter is sent with: its example, its default or its first allowed
+/// value, from the parameter itself or from its schema, or a sample value for its type.
+fn sample_value(document: &Value, param: &Value) -> String {
+    let schema = param.get("schema").map(|schema| resolve(document, schema));
+    let sources = [Some(param), schema];
+    for source in sources.iter().flatten() {
+        let first_example = source
+            .get("examples")
+            .and_then(Value::as_object)
+            .and_then(|examples| examples.values().next())
+            .map(|example| example.get("value").unwrap_or(example));
+        let value = source
+            .get("example")
+            .or_else(|| source.get("default"))
+            .or_else(|| source.get("enum").and_then(|values| values.get(0)))
+            .or(first_example);
+        match value {
+            Some(Value::String(text)) => return text.clone(),
+            Some(value @ (Value::Number(_) | Value::Bool(_))) => return value.to_string(),
+            _ => {}
+        }
+    }
+
+    let field = |name: &str| {
+        sources
+            .iter()
+            .flatten()
+            .find_map(|source| source.get(name).and_then(Value::as_str))
+            .unwrap_or_default()
+    };
+    match (field("type"), field("format")) {
+        ("integer", _) | ("number", _) => "1".to_string(),
+        ("boolean", _) => "true".to_string(),
+        (_, "uuid") => "00000000-0000-0000-0000-000000000000".to_string(),
+        (_, "date") => "2024-01-01".to_string(),
+        (_, "date-time") => "2024-01-01T00:00:00Z".to_string(),
+        (_, "email") => "test@example.com".to_string(),
+        _ => "test".to_string(),
+    }
+}
+
+/// Follows `value` while it is a local `$ref` like "#/components/parameters/id". A reference that
+/// can't be followed is returned as it is.
+fn resolve<'a>(document: &'a Value, mut value: &'a Value) -> &'a Value {
+    for _ in 0..MAX_REFS {
+        let pointer = match value.get("$ref").and_then(Value::as_str) {
+            Some(reference) => match reference.strip_prefix('#') {
+                Some(pointer) => pointer,
+                None => break,
+            },
+            None => break,
+        };
+        match document.pointer(pointer) {
+            Some(target) => value = target,
+            None => break,
+        }
+    }
+    value
+}
+
+/// Returns true if a `security` list asks for any credentials. An empty list, or one with an
+/// empty requirement in it, means the operation can be called without any.
+fn is_secured(security: Option<&Value>) -> bool {
+    match security.and_then(Value::as_array) {
+        Some(requirements) => {
+            !requirements.is_empty()
+                && requirements.iter().all(|requirement| {
+                    requirement
+                        .as_object()
+                        .is_some_and(|requirement| !requirement.is_empty())
+                })
+        }
+        None => false,
+    }
+}
+
+/// Returns the base path every documented path is under: the path of the first server of an
+/// OpenAPI 3 description, with its variables set to their defaults, or the `basePath` of a
+/// Swagger 2.0 description.
+fn base_path(document: &Value) -> String {
+    if let Some(base_path) = document.get("basePath").and_then(Value::as_str) {
+        return base_path.to_string();
+    }
+    let server = match document.pointer("/servers/0") {
+        Some(server) => server,
+        None => return String::new(),
+    };
+    let mut url = server
+        .get("url")
+        .and_then(Value::as_str)
+        .unwrap_or_default()
+        .to_string();
+    if let Some(variables) = server.get("variables").and_then(Value::as_object) {
+        for (name, variable) in variables {
+            let default = variable
+                .get("default")
+                .and_then(Value::as_str)
+                .unwrap_or_default();
+            url = url.replace(&format!("{{{}}}", name), default);
+        }
+    }
+    // The server can be a full url or a path relative to the host the description is on.
+    match Url::parse(&url) {
+        Ok(url) => url.path().to_string(),
+        Err(_) => url,
+    }
+}
+
+/// Joins a documented path onto the base path.
+fn join_paths(base_path: &str, path: &str) -> String {
+    format!(
+        "/{}/{}",
+        base_path.trim_matches('/'),
+        path.trim_start_matches('/')
+    )
+    .replace("//", "/")
+}
+
+/// A documented operation as it was requested by the scan.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ApiEndpoint {
+    pub operation: ApiOperation,
+
+    // The full url that was requested.
+    pub url: String,
+
+    // The status code the endpoint answered with, if its result was kept.
+    pub status: Option<u16>,
+}
+
+impl ApiEndpoint {
+    /// Returns true if the endpoint answered with a success or a redirect, without credentials.
+    pub fn is_reachable(&self) -> bool {
+        self.status.is_some_and(|status| status < 400)
+    }
+
+    /// Returns the key of the endpoint's result in `Scanner::found`.
+    pub fn key(&self) -> ResultKey {
+        ResultKey {
+            method: self.operation.method.clone(),
+            url: self.url.clone(),
+            host: None,
+        }
+    }
+}
+
+/// Fetches the text at `url` with a single GET request. Returns None if it doesn't answer with a
+/// 200.
+async fn fetch(client: &Client, url: &str, audit: Option<&AuditLog>) -> Option<String> {
+    let policy = RetryPolicy {
+        max_attempts: 1,
+        ..Default::default()
+    };
+    let probe = Probe {
+        name: url.to_string(),
+        url: url.to_string(),
+        word: url.to_string(),
+        ..Default::default()
+    };
+    let outcome = send_probe(client, probe, &policy, true, audit).await;
+    match outcome.result {
+        Some(result) if result.status == 200 => {
+            Some(String::from_utf8_lossy(&outcome.body?).to_string())
+        }
+        _ => None,
+    }
+}
+
+impl Scanner {
+    /// Loads the API descriptions at `locations`, which are urls or paths of local files. When
+    /// there are none, every one of the `COMMON_SPEC_PATHS` under the site is requested and every
+    /// description found is kept. Requests are checked against the scope like any other. Returns
+    /// an error if a description that was asked for can't be read or parsed.
+    pub async fn load_api_specs(
+        &mut self,
+        client: &Client,
+        locations: &[String],
+    ) -> Result<Vec<ApiSpec>, String> {
+        let audit = self.audit.clone();
+        let audit = audit.as_deref();
+        let mut specs = Vec::new();
+        for location in locations {
+            let text = match location.starts_with("http://") || location.starts_with("https://") {
+                true => {
+                    if self.check_scope(location).is_err() {
+                        return Err(format!("The API description {} is out of scope.", location));
+                    }
+                    fetch(client, location, audit).await.ok_or_else(|| {
+                        format!("Could not download the API description {}", location)
+                    })?
+                }
+                false => fs::read_to_string(location).map_err(|err| {
+                    format!("Could not read the API description {}: {}", location, err)
+                })?,
+            };
+            specs.push(ApiSpec::parse(location, &text)?);
+        }
+        if !locations.is_empty() {
+            return Ok(specs);
+        }
+
+        let target = Target::parse(&self.site)?;
+        for path in COMMON_SPEC_PATHS {
+            let url = match target.join(path) {
+                Ok(url) => url,
+                Err(_) => continue,
+            };
+            if self.check_scope(&url).is_err() {
+                continue;
+            }
+            if let Some(text) = fetch(client, &url, audit).await {
+                // Pages that answer every path, like a single page app, aren't descriptions.
+                if let Ok(spec) = ApiSpec::parse(&url, &text) {
+                    specs.push(spec);
+                }
+            }
+        }
+        Ok(specs)
+    }
+
+    /// Builds a probe for every operation of the `specs` on the host of the site, and keeps each
+    /// one in `api_endpoints`. Operations that can change data are only sent when `send_unsafe`
+    /// is true, and are stored in `skipped` otherwise. Operations that are documented twice, or
+    /// that are already among the `existing` probes, aren't requested again.
+    pub fn api_probes(
+        &mut self,
+        specs: &[ApiSpec],
+        send_unsafe: bool,
+        existing: &[Probe],
+    ) -> Result<Vec<Probe>, String> {
+        let site = Target::parse(&self.site)?;
+        let origin = Target::parse(&site.url().origin().ascii_serialization())?;
+        let existing: HashSet<(&str, &str)> = existing
+            .iter()
+            .map(|probe| (probe.method(), probe.url.as_str()))
+            .collect();
+        let mut documented = HashSet::new();
+
+        let mut probes = Vec::new();
+        for operation in specs.iter().flat_map(|spec| &spec.operations) {
+            let url = match origin.join(&operation.sample_path) {
+                Ok(url) => url,
+                Err(url) => {
+                    self.skipped.push(SkippedRequest {
+                        url,
+                        reason: SkipReason::DotSegment,
+                    });
+                    continue;
+                }
+            };
+            let mut url = Url::parse(&url)
+                .map_err(|err| format!("Could not build the url of {}: {}", operation.path, err))?;
+            if !operation.query.is_empty() {
+                url.query_pairs_mut().extend_pairs(&operation.query);
+            }
+            let url = url.to_string();
+            if !documented.insert((operation.method.clone(), url.clone())) {
+                continue;
+            }
+            if !send_unsafe && !SAFE_METHODS.contains(&operation.method.as_str()) {
+                self.skipped.push(SkippedRequest {
+                    url,
+                    reason: SkipReason::Dangerous(operation.method.clone()),
+                });
+                continue;
+            }
+
+            // An endpoint the wordlist already requests is checked from the result of that request.
+            if !existing.contains(&(operation.method.as_str(), url.as_str())) {
+                let word = operation.sample_path.trim_start_matches('/').to_string();
+                probes.push(Probe {
+                    name: word.clone(),
+                    url: url.clone(),
+                    host: None,
+                    word,
+                    form: None,
+                    method: Some(operation.method.clone()),
+                    json: None,
+                });
+            }
+            self.api_endpoints.push(ApiEndpoint {
+                operation: operation.clone(),
+                url,
+                status: None,
+            });
+        }
+        Ok(probes)
+    }
+
+    /// Records the status code every endpoint in `api_endpoints` answered with, once the scan is
+    /// done, and tags its result: "unauthenticated" if it answered with a success or a redirect,
+    /// along with "documented as secured" if the description says it needs credentials, and
+    /// "auth required" if it answered 401 or 403. Returns the number of endpoints that were
+    /// reachable without credentials.
+    pub fn check_api_endpoints(&mut self) -> usize {
+        let mut reachable = 0;
+        for endpoint in &mut self.api_endpoints {
+            let mut result = match self.found.get(&endpoint.key()) {
+                Some(result) => result.clone(),
+                None => continue,
+            };
+            endpoint.status = Some(result.status);
+            if endpoint.is_reachable() {
+                reachable += 1;
+                result.tags.push("unauthenticated".to_string());
+                if endpoint.operation.secured {
+                    result.tags.push("documented as secured".to_string());
+                }
+            } else if result.status == 401 || result.status == 403 {
+                result.tags.push("auth required".to_string());
+            }
+            self.found.insert(result);
+        }
+        reachable
+    }
+}
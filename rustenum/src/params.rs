@@ -0,0 +1,305 @@
+//! Hidden parameter discovery for the `params` scan mode.
+//!
+//! Every word in the wordlist is a candidate parameter name for a single known endpoint. Instead
+//! of sending one request per name, many names are sent together in one request and the response
+//! is compared with a baseline response. A batch whose response differs, by status code or by
+//! length, is split in half and both halves are sent again until the single names causing the
+//! difference are found. Every name gets its own value, so a value showing up in the response
+//! points straight at the parameter that was reflected.
+
+use crate::results::ScanResult;
+use crate::scan::{send_probe, Probe, ProbeOutcome};
+use crate::{ResponseDetails, Scanner};
+use futures::{stream, StreamExt};
+use indicatif::ProgressBar;
+use reqwest::{Client, Url};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+/// The number of parameter names sent in each request when none is configured.
+pub const DEFAULT_BATCH_SIZE: usize = 32;
+
+/// The parameter name sent with the baseline requests. It shouldn't mean anything to the server.
+const BASELINE_NAME: &str = "rustenumbaseline";
+
+/// Where the candidate parameters are sent.
+#[derive(Default, Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ParamMethod {
+    // In the query string of a GET request.
+    #[default]
+    Get,
+
+    // In the url encoded form body of a POST request.
+    Post,
+}
+
+impl ParamMethod {
+    /// Parses the name of an HTTP method as it is written in config files and on the command line.
+    pub fn parse(name: &str) -> Result<Self, String> {
+        match name.to_uppercase().as_str() {
+            "GET" => Ok(ParamMethod::Get),
+            "POST" => Ok(ParamMethod::Post),
+            _ => Err("the method needs to be GET or POST".to_string()),
+        }
+    }
+
+    /// Returns the name of the HTTP method, like "GET".
+    pub fn name(&self) -> &'static str {
+        match self {
+            ParamMethod::Get => "GET",
+            ParamMethod::Post => "POST",
+        }
+    }
+}
+
+/// The parts of a response that are compared with the baseline.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ResponseSignature {
+    pub status: u16,
+
+    // The length of the body after every parameter that was sent is removed from it, so pages
+    // that echo the request url don't look different for every batch.
+    pub length: usize,
+}
+
+/// Returns the value sent for the word at `index` of the wordlist. The value is wrapped in
+/// letters on both sides so no value is a part of another one.
+pub fn canary(index: usize) -> String {
+    format!("rustenum{}z", index)
+}
+
+/// Builds the signature of a response with `status` and `body` to a request that sent `params`.
+pub fn signature(status: u16, body: &str, params: &[(String, String)]) -> ResponseSignature {
+    let mut stripped = body.to_string();
+    for (name, value) in params {
+        // Remove the parameter as it appears in an echoed query string, separator included, and
+        // then any other place the value shows up.
+        let pair = format!("{}={}", name, value);
+        stripped = stripped.replace(&format!("&{}", pair), "");
+        stripped = stripped.replace(&pair, "");
+        stripped = stripped.replace(value.as_str(), "");
+    }
+    ResponseSignature {
+        status,
+        length: stripped.len(),
+    }
+}
+
+/// Returns the names in `params` whose value shows up in `body`.
+pub fn reflected<'a>(body: &str, params: &'a [(String, String)]) -> Vec<&'a str> {
+    params
+        .iter()
+        .filter(|(_, value)| body.contains(value.as_str()))
+        .map(|(name, _)| name.as_str())
+        .collect()
+}
+
+/// Why a parameter was reported.
+#[derive(Debug, Default)]
+struct ParamFinding {
+    status: u16,
+    reasons: Vec<String>,
+    details: ResponseDetails,
+}
+
+impl Scanner {
+    /// Builds the probe that sends the words at `indexes` of the wordlist as parameters of the
+    /// base url, each with its own canary value.
+    fn param_probe(&self, indexes: &[usize]) -> Probe {
+        let params: Vec<(String, String)> = indexes
+            .iter()
+            .map(|index| (self.wordlist.0[*index].clone(), canary(*index)))
+            .collect();
+        let name = indexes
+            .iter()
+            .map(|index| self.wordlist.0[*index].as_str())
+            .collect::<Vec<&str>>()
+            .join(",");
+        self.params_probe(name, &params)
+    }
+
+    /// Builds a probe that sends `params` to the base url with the scanner's parameter method.
+    fn params_probe(&self, name: String, params: &[(String, String)]) -> Probe {
+        let mut url = Url::parse(&self.site).expect("the site is parsed when it is added");
+        let form = match self.param_method {
+            ParamMethod::Get => {
+                url.query_pairs_mut().extend_pairs(params);
+                None
+            }
+            ParamMethod::Post => Some(params.to_vec()),
+        };
+        Probe {
+            name: name.clone(),
+            url: url.to_string(),
+            host: None,
+            word: name,
+            form,
+            method: None,
+            json: None,
+        }
+    }
+
+    /// Looks for parameters of the base url that change its response. The words of the wordlist
+    /// are sent `batch_size` at a time with at most `thread_count` requests in flight, and every
+    /// batch that changes the response is split until the parameters responsible are found. They
+    /// are stored in `found` with the reasons they were reported. The `progress_bar` grows as
+    /// batches are split. Returns an error if the baseline request doesn't get a response.
+    pub async fn discover_params(
+        &mut self,
+        client: &Client,
+        thread_count: usize,
+        progress_bar: &ProgressBar,
+    ) -> Result<(), String> {
+        // Request the endpoint twice with a parameter that shouldn't mean anything. If the two
+        // responses don't have the same length, the length can't be used to spot differences.
+        let baseline_params = vec![(BASELINE_NAME.to_string(), canary(usize::MAX))];
+        let mut baselines = Vec::new();
+        for _ in 0..2 {
+            let probe = self.params_probe(BASELINE_NAME.to_string(), &baseline_params);
+            if let Err(reason) = self.check_scope(&probe.url) {
+                return Err(format!("The baseline request can't be sent: {}.", reason));
+            }
+            let outcome = send_probe(
+                client,
+                probe,
+                &self.retry_policy,
+                true,
+                self.audit.as_deref(),
+            )
+            .await;
+            let (result, body) = match (outcome.result, outcome.body) {
+                (Some(result), Some(body)) => (result, String::from_utf8_lossy(&body).to_string()),
+                _ => return Err("The baseline request didn't get a response.".to_string()),
+            };
+            baselines.push(signature(result.status, &body, &baseline_params));
+        }
+        let baseline = baselines[0];
+        let stable_length = baselines[1].length == baseline.length;
+        if !stable_length {
+            progress_bar.println(
+                "The response length changes between requests, only status codes and reflected values are compared.",
+            );
+        }
+
+        let batch_size = self.batch_size.unwrap_or(DEFAULT_BATCH_SIZE).max(1);
+        let indexes: Vec<usize> = (0..self.wordlist.len()).collect();
+        let mut pending: Vec<Vec<usize>> = indexes
+            .chunks(batch_size)
+            .map(|batch| batch.to_vec())
+            .collect();
+        progress_bar.set_length(pending.len() as u64);
+
+        let mut findings: BTreeMap<usize, ParamFinding> = BTreeMap::new();
+        while !pending.is_empty() {
+            // Batches out of scope or over the request budget are never sent.
+            let mut batches: Vec<(Vec<usize>, Probe)> = Vec::new();
+            for batch in std::mem::take(&mut pending) {
+                let probe = self.param_probe(&batch);
+                match self.check_scope(&probe.url) {
+                    Ok(()) => batches.push((batch, probe)),
+                    Err(_) => progress_bar.inc(1),
+                }
+            }
+
+            let policy = &self.retry_policy;
+            let audit = self.audit.as_deref();
+            let outcomes: Vec<(Vec<usize>, ProbeOutcome)> = stream::iter(batches)
+                .map(|(batch, probe)| async move {
+                    let outcome = send_probe(client, probe, policy, true, audit).await;
+                    progress_bar.inc(1);
+                    (batch, outcome)
+                })
+                .buffer_unordered(thread_count)
+                .collect()
+                .await;
+
+            for (batch, outcome) in outcomes {
+                let (result, body) = match (outcome.result, outcome.body) {
+                    (Some(result), Some(body)) => {
+                        (result, String::from_utf8_lossy(&body).to_string())
+                    }
+                    _ => {
+                        // Every word of a batch that never got a response is scanned again later.
+                        for index in &batch {
+                            self.unreachable.push(self.wordlist.0[*index].clone());
+                        }
+                        continue;
+                    }
+                };
+                if let Some(total_ms) = result.details.total_ms {
+                    self.latencies.push(total_ms);
+                }
+                let mut details = result.details;
+                if let Some(signatures) = &self.signatures {
+                    details.findings = signatures.scan(&body);
+                }
+
+                let params: Vec<(String, String)> = batch
+                    .iter()
+                    .map(|index| (self.wordlist.0[*index].clone(), canary(*index)))
+                    .collect();
+                let status = result.status;
+
+                // A reflected value points straight at its parameter, so there's no need to split.
+                for name in reflected(&body, &params) {
+                    let index = batch[params.iter().position(|(n, _)| n == name).unwrap()];
+                    let finding = findings.entry(index).or_default();
+                    finding.status = status;
+                    finding.details = details.clone();
+                    if !finding.reasons.contains(&"REFLECTED".to_string()) {
+                        finding.reasons.push("REFLECTED".to_string());
+                    }
+                }
+
+                // A different status code usually comes with a different body, so the length is
+                // only compared when the status code is the same.
+                let current = signature(status, &body, &params);
+                let reason = if current.status != baseline.status {
+                    format!("STATUS {}", current.status)
+                } else if stable_length && current.length != baseline.length {
+                    format!("LENGTH {}", current.length)
+                } else {
+                    continue;
+                };
+
+                if batch.len() == 1 {
+                    let finding = findings.entry(batch[0]).or_default();
+                    finding.status = status;
+                    finding.details = details.clone();
+                    finding.reasons.push(reason);
+                } else {
+                    // Split the batch in half and send both halves again.
+                    let (first, second) = batch.split_at(batch.len() / 2);
+                    pending.push(first.to_vec());
+                    pending.push(second.to_vec());
+                    progress_bar.inc_length(2);
+                }
+            }
+        }
+
+        // Store every parameter under its name, with the reasons it was reported. The reasons
+        // aren't known until every batch is done, so the hits are only reported now. The url of
+        // a parameter always has the parameter in its query, even when it was sent in a form
+        // body, so every parameter is its own result.
+        let mut live_output = self.open_live_output(progress_bar);
+        for (index, finding) in findings {
+            let name = self.wordlist.0[index].clone();
+            let mut url = Url::parse(&self.site).expect("the site is parsed when it is added");
+            url.query_pairs_mut().append_pair(&name, &canary(index));
+            let result = ScanResult {
+                name,
+                method: self.param_method.name().to_string(),
+                url: url.to_string(),
+                host: None,
+                status: finding.status,
+                tags: finding.reasons,
+                details: finding.details,
+            };
+            if self.add_to_found(result.clone()) {
+                self.report_hit(&result, progress_bar, &mut live_output);
+            }
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,311 @@
+//! Response plugins: analyzers written outside of rustenum.
+//!
+//! A `ResponsePlugin` is given every response a `dir`, `vhost` or `fuzz` scan receives: the url,
+//! the method, the status code, the headers and the body. It answers with an `Analysis` that can
+//! annotate the result with tags, reclassify it so it is kept or ignored whatever its status code,
+//! and enqueue new paths to request. Plugins are registered on a `Scanner` with `add_plugin`.
+//!
+//! A `CommandPlugin` runs an external program as a plugin, so analyzers can be scripted in any
+//! language without building rustenum. The program is started once for the scan. It is sent one
+//! line of JSON for every response on its standard input, and answers each one with a line of JSON
+//! on its standard output, like `{"tags": ["debug page"], "classification": "keep"}`. A program
+//! that doesn't answer within `PLUGIN_TIMEOUT` is stopped.
+
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::io::{BufRead, BufReader, Write};
+use std::process::{Child, Command, Stdio};
+use std::sync::mpsc::{self, Receiver, RecvTimeoutError, Sender};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::Duration;
+
+/// The most rounds of enqueued paths that are requested after the wordlist. Paths enqueued by
+/// the responses of the last round are left out, so plugins can't keep a scan running forever.
+pub const MAX_FOLLOW_UP_ROUNDS: usize = 5;
+
+/// A response as it is given to a plugin.
+#[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PluginResponse {
+    pub url: String,
+
+    pub method: String,
+
+    // The HTTP status code of the response.
+    pub status: u16,
+
+    // Every header of the response as a name and value, in the order they were received.
+    pub headers: Vec<(String, String)>,
+
+    // The body of the response. Bytes that aren't valid UTF-8 are replaced when it is sent to a
+    // `CommandPlugin`.
+    #[serde(with = "lossy_body")]
+    pub body: Vec<u8>,
+}
+
+impl PluginResponse {
+    /// Returns the value of the first header named `name`, ignoring case.
+    pub fn header(&self, name: &str) -> Option<&str> {
+        self.headers
+            .iter()
+            .find(|(header, _)| header.eq_ignore_ascii_case(name))
+            .map(|(_, value)| value.as_str())
+    }
+
+    /// Returns the body as text. Bytes that aren't valid UTF-8 are replaced.
+    pub fn text(&self) -> String {
+        String::from_utf8_lossy(&self.body).to_string()
+    }
+}
+
+/// Bodies are sent to external plugins as text, since JSON has no bytes.
+mod lossy_body {
+    use serde::{Deserialize, Deserializer, Serializer};
+
+    pub fn serialize<S: Serializer>(body: &[u8], serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&String::from_utf8_lossy(body))
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Vec<u8>, D::Error> {
+        Ok(String::deserialize(deserializer)?.into_bytes())
+    }
+}
+
+/// Whether a plugin wants a response kept as a result.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Classification {
+    // Keep the result, even if its status code is ignored.
+    Keep,
+
+    // Ignore the result, even if its status code would be kept.
+    Ignore,
+}
+
+/// What a plugin has to say about a response.
+#[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Analysis {
+    // Labels added to the result, shown after its name.
+    pub tags: Vec<String>,
+
+    // Whether the result is kept, whatever its status code. The ignore and match lists decide
+    // when this isn't set.
+    pub classification: Option<Classification>,
+
+    // Paths to request under the base url after the wordlist, like words of the wordlist. Paths
+    // are only followed up in `dir` scans.
+    pub enqueue: Vec<String>,
+}
+
+impl Analysis {
+    /// Adds the analysis of another plugin. Tags and paths are added up, and the classification
+    /// of the later plugin wins.
+    pub fn merge(&mut self, other: Analysis) {
+        self.tags.extend(other.tags);
+        if other.classification.is_some() {
+            self.classification = other.classification;
+        }
+        self.enqueue.extend(other.enqueue);
+    }
+}
+
+/// An analyzer that is given every response of a scan. Responses arrive from several requests at
+/// once, so plugins have to be shareable between threads, and they should be quick, since the
+/// scan waits for them. Plugins are run on a blocking thread, so they may block.
+pub trait ResponsePlugin: Send + Sync {
+    /// The name the plugin's errors are reported under.
+    fn name(&self) -> &str;
+
+    /// Analyzes a single response. An error is reported above the progress bar, and the response
+    /// is handled as if the plugin had nothing to say.
+    fn analyze(&self, response: &PluginResponse) -> Result<Analysis, String>;
+}
+
+/// The plugins registered on a scanner, run in the order they were added.
+#[derive(Default, Clone)]
+pub struct PluginSet {
+    plugins: Vec<Arc<dyn ResponsePlugin>>,
+}
+
+impl PluginSet {
+    /// Adds a plugin after the ones already registered.
+    pub fn add(&mut self, plugin: Arc<dyn ResponsePlugin>) {
+        self.plugins.push(plugin);
+    }
+
+    /// Returns true if no plugins are registered.
+    pub fn is_empty(&self) -> bool {
+        self.plugins.is_empty()
+    }
+
+    /// Returns the number of registered plugins.
+    pub fn len(&self) -> usize {
+        self.plugins.len()
+    }
+
+    /// Runs every plugin on `response` and merges what they had to say. The error of every plugin
+    /// that failed is returned with its name.
+    pub fn analyze(&self, response: &PluginResponse) -> (Analysis, Vec<String>) {
+        let mut analysis = Analysis::default();
+        let mut errors = Vec::new();
+        for plugin in &self.plugins {
+            match plugin.analyze(response) {
+                Ok(other) => analysis.merge(other),
+                Err(err) => errors.push(format!("Plugin {}: {}", plugin.name(), err)),
+            }
+        }
+        (analysis, errors)
+    }
+}
+
+impl fmt::Debug for PluginSet {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_list()
+            .entries(self.plugins.iter().map(|plugin| plugin.name()))
+            .finish()
+    }
+}
+
+/// Splits the status line and headers of a response, as kept by the scan engine, into the names
+/// and values of the headers.
+pub fn parse_headers(head: &str) -> Vec<(String, String)> {
+    head.lines()
+        .skip(1)
+        .filter_map(|line| line.split_once(':'))
+        .map(|(name, value)| (name.trim().to_string(), value.trim().to_string()))
+        .collect()
+}
+
+/// How long a `CommandPlugin` has to answer a response before it is stopped.
+pub const PLUGIN_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// A plugin that runs an external program, started once with `sh -c` and kept running for the
+/// whole scan.
+pub struct CommandPlugin {
+    // The command the program was started with.
+    command: String,
+
+    // How long the program has to answer each response.
+    timeout: Duration,
+
+    // The running program. It is stopped after the first time it fails to answer.
+    process: Mutex<Option<PluginProcess>>,
+}
+
+struct PluginProcess {
+    child: Child,
+
+    // The lines to write to the program's standard input. They are written by a thread of their
+    // own, so a program that stops reading can't block the scan.
+    lines: Sender<String>,
+
+    // The lines the program wrote to its standard output, read by a thread of their own.
+    answers: Receiver<String>,
+}
+
+impl CommandPlugin {
+    /// Starts `command` with `sh -c`, with `PLUGIN_TIMEOUT` to answer each response. Returns an
+    /// error if it can't be started.
+    pub fn start(command: &str) -> Result<Self, String> {
+        let mut child = Command::new("sh")
+            .arg("-c")
+            .arg(command)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .spawn()
+            .map_err(|err| format!("Could not start the plugin `{}`: {}", command, err))?;
+        let (mut stdin, stdout) = match (child.stdin.take(), child.stdout.take()) {
+            (Some(stdin), Some(stdout)) => (stdin, stdout),
+            _ => return Err(format!("Could not start the plugin `{}`", command)),
+        };
+
+        // Both threads end once the program exits and its pipes are closed.
+        let (lines, unsent) = mpsc::channel::<String>();
+        thread::spawn(move || {
+            for line in unsent {
+                if writeln!(stdin, "{}", line)
+                    .and_then(|_| stdin.flush())
+                    .is_err()
+                {
+                    break;
+                }
+            }
+        });
+        let (read, answers) = mpsc::channel();
+        thread::spawn(move || {
+            for line in BufReader::new(stdout).lines().map_while(Result::ok) {
+                if read.send(line).is_err() {
+                    break;
+                }
+            }
+        });
+
+        Ok(CommandPlugin {
+            command: command.to_string(),
+            timeout: PLUGIN_TIMEOUT,
+            process: Mutex::new(Some(PluginProcess {
+                child,
+                lines,
+                answers,
+            })),
+        })
+    }
+
+    /// Sets how long the program has to answer each response.
+    pub fn with_timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = timeout;
+        self
+    }
+}
+
+impl ResponsePlugin for CommandPlugin {
+    fn name(&self) -> &str {
+        &self.command
+    }
+
+    /// Sends the response to the program and waits up to the plugin's timeout for its answer.
+    /// An answer that can't be parsed is only an error for this response. Once the program exits
+    /// or fails to answer in time, it is stopped and the rest of the responses aren't sent to it,
+    /// so the error is only reported once.
+    fn analyze(&self, response: &PluginResponse) -> Result<Analysis, String> {
+        let mut process = self
+            .process
+            .lock()
+            .map_err(|_| "the plugin stopped".to_string())?;
+        let running = match process.as_mut() {
+            Some(running) => running,
+            None => return Ok(Analysis::default()),
+        };
+        let line = serde_json::to_string(response)
+            .map_err(|err| format!("could not send the response: {}", err))?;
+        let error = match running.lines.send(line) {
+            Ok(()) => match running.answers.recv_timeout(self.timeout) {
+                Ok(answer) => {
+                    return serde_json::from_str(&answer)
+                        .map_err(|err| format!("could not parse its answer: {}", err))
+                }
+                Err(RecvTimeoutError::Timeout) => format!("no answer within {:?}", self.timeout),
+                Err(RecvTimeoutError::Disconnected) => "the program exited".to_string(),
+            },
+            Err(_) => "could not send the response: the program exited".to_string(),
+        };
+        if let Some(mut stopped) = process.take() {
+            let _ = stopped.child.kill();
+            let _ = stopped.child.wait();
+        }
+        Err(error)
+    }
+}
+
+impl Drop for CommandPlugin {
+    fn drop(&mut self) {
+        if let Ok(process) = self.process.get_mut() {
+            if let Some(process) = process.as_mut() {
+                // The program is only needed for as long as the scan runs.
+                let _ = process.child.kill();
+                let _ = process.child.wait();
+            }
+        }
+    }
+}
@@ -0,0 +1,594 @@
+//! Scan reports built from the results stored in a `Scanner`.
+//!
+//! A `ScanReport` is the result model shared by the structured output formats. It can be written
+//! to a JSON file, read back in after the fact, and rendered as a self-contained HTML page.
+
+use crate::analysis::BodyFinding;
+use crate::bypass::BypassResult;
+use crate::graphql::GraphqlEndpoint;
+use crate::openapi::ApiEndpoint;
+use crate::scan::ScanMode;
+use crate::timing::LatencySummary;
+use crate::{Scanner, StatusMatcher};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+use std::fs;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+/// The settings that were used to run a scan.
+#[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ReportConfig {
+    // The base url that was enumerated.
+    pub site: String,
+
+    // The HTTP method used for every request.
+    pub method: String,
+
+    // HTTP status codes that were ignored during the scan.
+    pub ignore_list: StatusMatcher,
+
+    // The only HTTP status codes that were kept, if the scan was limited to some.
+    #[serde(default)]
+    pub match_list: StatusMatcher,
+
+    // File extensions that were appended to each word.
+    pub extensions: Vec<String>,
+
+    // The number of pages requested, including the extension variants.
+    pub wordlist_size: usize,
+
+    // The number of concurrent requests used.
+    pub threads: usize,
+
+    // The kind of scan that was run.
+    #[serde(default)]
+    pub mode: ScanMode,
+}
+
+/// A single page found during the scan.
+#[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ReportEntry {
+    // The page that was requested, without a leading '/'. For `vhost` and `fuzz` scans this
+    // is the virtual host name or the fuzzed word.
+    pub path: String,
+
+    // The HTTP method the page was requested with.
+    #[serde(default = "default_method")]
+    pub method: String,
+
+    // The full url that was requested. Reports written by older versions don't have it.
+    #[serde(default)]
+    pub url: String,
+
+    // The `Host` header that was sent instead of the host from the url.
+    #[serde(default)]
+    pub host: Option<String>,
+
+    // The HTTP status code received for the page.
+    pub status: u16,
+
+    // Where the page redirected to, if it was a redirect.
+    pub redirect: Option<String>,
+
+    // The size of the response body in bytes, if it is known.
+    pub size: Option<u64>,
+
+    // Milliseconds until the response headers arrived.
+    #[serde(default)]
+    pub ttfb_ms: Option<f64>,
+
+    // Milliseconds until the whole response was read.
+    #[serde(default)]
+    pub total_ms: Option<f64>,
+
+    // True if the page was dramatically slower than the rest of the scan.
+    #[serde(default)]
+    pub slow: bool,
+
+    // The signatures that matched the response body.
+    #[serde(default)]
+    pub findings: Vec<BodyFinding>,
+
+    // Labels added to the result, like the reasons a parameter was reported or the tags of
+    // plugins, bypasses and API endpoints.
+    #[serde(default)]
+    pub tags: Vec<String>,
+
+    // The file in the response directory the raw response was saved to.
+    #[serde(default)]
+    pub saved_as: Option<String>,
+}
+
+/// The results of a scan along with the configuration and timing information of the scan.
+#[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ScanReport {
+    pub config: ReportConfig,
+
+    // When the scan started, in seconds since the Unix epoch.
+    pub started_at: u64,
+
+    // How long the scan took, in seconds.
+    pub elapsed_secs: f64,
+
+    // Percentiles of the response times of every response received during the scan.
+    #[serde(default)]
+    pub latency: Option<LatencySummary>,
+
+    // Every stored result, sorted by HTTP status code in ascending order.
+    pub results: Vec<ReportEntry>,
+
+    // Words that never got a response, even after every retry.
+    #[serde(default)]
+    pub unreachable: Vec<String>,
+
+    // The variants of forbidden results that got another status code.
+    #[serde(default)]
+    pub bypasses: Vec<BypassResult>,
+
+    // The documented API operations that were requested, with the status code each answered with.
+    #[serde(default)]
+    pub api_endpoints: Vec<ApiEndpoint>,
+
+    // The GraphQL endpoints that were found, with whether introspection is enabled on each.
+    #[serde(default)]
+    pub graphql_endpoints: Vec<GraphqlEndpoint>,
+}
+
+/// Returns the class of the given HTTP status code, like "2xx" or "4xx". Codes outside of
+/// the 100-599 range are put into the "other" class.
+pub fn status_class(status: u16) -> &'static str {
+    match status {
+        100..=199 => "1xx",
+        200..=299 => "2xx",
+        300..=399 => "3xx",
+        400..=499 => "4xx",
+        500..=599 => "5xx",
+        _ => "other",
+    }
+}
+
+/// Reports written before the method was stored only sent GET requests.
+fn default_method() -> String {
+    "GET".to_string()
+}
+
+impl ReportEntry {
+    /// Returns what identifies the entry when two reports are compared: the method, the path and
+    /// the `Host` header. The path is used instead of the url since the url of the same page
+    /// changes when the site is moved.
+    pub fn key(&self) -> (&str, &str, Option<&str>) {
+        (&self.method, &self.path, self.host.as_deref())
+    }
+}
+
+impl ScanReport {
+    /// Builds a report from the results stored in `scanner`. The `threads`, `started` and `elapsed`
+    /// arguments describe how the scan was run, since the `Scanner` doesn't keep track of them.
+    pub fn new(scanner: &Scanner, threads: usize, started: SystemTime, elapsed: Duration) -> Self {
+        let latency = scanner.latency_summary();
+        // The results are sorted the same way as by `display_found`.
+        let results: Vec<ReportEntry> = scanner
+            .found
+            .sorted()
+            .into_iter()
+            .map(|result| {
+                let details = result.details.clone();
+                let slow = match (&latency, details.total_ms) {
+                    (Some(latency), Some(total_ms)) => latency.is_slow(total_ms),
+                    _ => false,
+                };
+                ReportEntry {
+                    path: result.name.clone(),
+                    method: result.method.clone(),
+                    url: result.url.clone(),
+                    host: result.host.clone(),
+                    status: result.status,
+                    redirect: details.location,
+                    size: details.content_length,
+                    ttfb_ms: details.ttfb_ms,
+                    total_ms: details.total_ms,
+                    slow,
+                    findings: details.findings,
+                    tags: result.tags.clone(),
+                    saved_as: details.saved_as,
+                }
+            })
+            .collect();
+
+        ScanReport {
+            config: ReportConfig {
+                site: scanner.site.to_string(),
+                method: scanner.method_name().to_string(),
+                ignore_list: scanner.status_code_ignore_list.clone(),
+                match_list: scanner.status_code_match_list.clone(),
+                extensions: scanner.extension_list.0.clone(),
+                wordlist_size: scanner.wordlist.len(),
+                threads,
+                mode: scanner.mode,
+            },
+            started_at: started
+                .duration_since(UNIX_EPOCH)
+                .map(|time| time.as_secs())
+                .unwrap_or(0),
+            elapsed_secs: elapsed.as_secs_f64(),
+            latency,
+            results,
+            unreachable: scanner.unreachable.clone(),
+            bypasses: scanner.bypasses.clone(),
+            api_endpoints: scanner.api_endpoints.clone(),
+            graphql_endpoints: scanner.graphql_endpoints.clone(),
+        }
+    }
+
+    /// Reads a report that was previously saved with `write_json`.
+    pub fn from_json_file(path: &str) -> Result<Self, String> {
+        let contents = fs::read_to_string(path)
+            .map_err(|err| format!("Could not read report file {}: {}", path, err))?;
+        serde_json::from_str(&contents)
+            .map_err(|err| format!("Could not parse report file {}: {}", path, err))
+    }
+
+    /// Returns the report serialized as pretty-printed JSON.
+    pub fn to_json(&self) -> String {
+        // Serializing plain structs of strings and numbers cannot fail.
+        serde_json::to_string_pretty(self).unwrap()
+    }
+
+    /// Writes the report as JSON to the file at `path`, replacing the file if it exists.
+    pub fn write_json(&self, path: &str) -> Result<String, String> {
+        match fs::write(path, self.to_json()) {
+            Ok(_) => Ok("Successfully wrote JSON results to ".to_string() + path),
+            Err(_err) => Err("Couldn't write JSON results to ".to_string() + path),
+        }
+    }
+
+    /// Writes the report as a self-contained HTML page to the file at `path`, replacing the
+    /// file if it exists.
+    pub fn write_html(&self, path: &str) -> Result<String, String> {
+        match fs::write(path, self.to_html()) {
+            Ok(_) => Ok("Successfully wrote HTML report to ".to_string() + path),
+            Err(_err) => Err("Couldn't write HTML report to ".to_string() + path),
+        }
+    }
+
+    /// Counts the stored results in each status class. Every class is present in the returned map,
+    /// even if no results fall into it.
+    pub fn status_class_counts(&self) -> BTreeMap<&'static str, usize> {
+        let mut counts = BTreeMap::new();
+        for class in ["1xx", "2xx", "3xx", "4xx", "5xx", "other"] {
+            counts.insert(class, 0);
+        }
+        for entry in &self.results {
+            *counts.entry(status_class(entry.status)).or_insert(0) += 1;
+        }
+        counts
+    }
+
+    /// Follows the redirect of `entry` through the other results of the scan. Redirects are never
+    /// followed during the scan, but if a redirect points at another page that was also found
+    /// then that page's redirect is the next hop in the chain. Returns every location in order.
+    pub fn redirect_chain(&self, entry: &ReportEntry) -> Vec<String> {
+        // Index every result by both its full url and its absolute path, since a `Location`
+        // header can hold either one.
+        let base = self.config.site.trim_end_matches('/');
+        let mut by_location: HashMap<String, &ReportEntry> = HashMap::new();
+        for result in &self.results {
+            by_location.insert(format!("{}/{}", base, result.path), result);
+            by_location.insert(format!("/{}", result.path), result);
+        }
+
+        let mut chain: Vec<String> = Vec::new();
+        let mut next = entry.redirect.clone();
+        while let Some(location) = next {
+            // Stop if the redirects loop back around on themselves.
+            if chain.contains(&location) {
+                break;
+            }
+            next = by_location
+                .get(&location)
+                .and_then(|result| result.redirect.clone());
+            chain.push(location);
+        }
+        chain
+    }
+
+    /// Renders the report as a single HTML page with inline styles and scripts, so the file can
+    /// be opened or shared without anything else alongside it.
+    pub fn to_html(&self) -> String {
+        let mut html = String::new();
+
+        html += "<!DOCTYPE html>\n<html lang=\"en\">\n<head>\n<meta charset=\"utf-8\">\n";
+        html += &format!(
+            "<title>RustEnum Report - {}</title>\n",
+            escape_html(&self.config.site)
+        );
+        html += REPORT_STYLE;
+        html += "</head>\n<body>\n";
+        html += "<h1>RustEnum Scan Report</h1>\n";
+
+        // The configuration the scan was run with.
+        html += "<h2>Scan Configuration</h2>\n<table class=\"config\">\n";
+        let ignore_list = match self.config.match_list.is_empty() {
+            true => self.config.ignore_list.to_string(),
+            false => format!(
+                "{}, only keeping {}",
+                self.config.ignore_list, self.config.match_list
+            ),
+        };
+        let extensions = if self.config.extensions.is_empty() {
+            "N/A".to_string()
+        } else {
+            self.config.extensions.join(", ")
+        };
+        let rows = [
+            ("Site", self.config.site.clone()),
+            ("Method", self.config.method.clone()),
+            ("Mode", format!("{:?}", self.config.mode)),
+            ("Ignoring", ignore_list),
+            ("Extensions", extensions),
+            ("Words requested", self.config.wordlist_size.to_string()),
+            ("Threads", self.config.threads.to_string()),
+            ("Started (Unix time)", self.started_at.to_string()),
+            ("Time elapsed", format!("{:.2} seconds", self.elapsed_secs)),
+        ];
+        for (name, value) in rows {
+            html += &format!(
+                "<tr><th>{}</th><td>{}</td></tr>\n",
+                name,
+                escape_html(&value)
+            );
+        }
+        html += "</table>\n";
+
+        // The number of results in each status class.
+        html += "<h2>Summary</h2>\n<table class=\"summary\">\n<tr>";
+        let counts = self.status_class_counts();
+        for class in counts.keys() {
+            html += &format!("<th>{}</th>", class);
+        }
+        html += "<th>total</th></tr>\n<tr>";
+        for count in counts.values() {
+            html += &format!("<td>{}</td>", count);
+        }
+        html += &format!("<td>{}</td></tr>\n</table>\n", self.results.len());
+
+        // The response time percentiles of the whole scan.
+        if let Some(latency) = &self.latency {
+            html += "<h2>Response Times (ms)</h2>\n<table class=\"summary\">\n";
+            html += "<tr><th>min</th><th>p50</th><th>p90</th><th>p95</th><th>p99</th><th>max</th><th>slow above</th></tr>\n";
+            html += &format!(
+                "<tr><td>{:.1}</td><td>{:.1}</td><td>{:.1}</td><td>{:.1}</td><td>{:.1}</td><td>{:.1}</td><td>{:.1}</td></tr>\n</table>\n",
+                latency.min_ms,
+                latency.p50_ms,
+                latency.p90_ms,
+                latency.p95_ms,
+                latency.p99_ms,
+                latency.max_ms,
+                latency.slow_threshold_ms
+            );
+        }
+
+        // The results table, which can be filtered and sorted by the script at the bottom.
+        html += "<h2>Results</h2>\n<div class=\"filters\">\n";
+        html += "<input id=\"filter\" type=\"text\" placeholder=\"Filter paths...\">\n";
+        html += "<select id=\"class\"><option value=\"\">All statuses</option>";
+        for class in counts.keys() {
+            html += &format!("<option value=\"{0}\">{0}</option>", class);
+        }
+        html += "</select>\n</div>\n";
+        html += "<table id=\"results\">\n<thead><tr>";
+        html += "<th data-type=\"text\">Path</th><th data-type=\"number\">Status</th>";
+        html += "<th data-type=\"number\">Size</th><th data-type=\"number\">TTFB (ms)</th>";
+        html +=
+            "<th data-type=\"number\">Total (ms)</th><th data-type=\"text\">Redirect Chain</th>";
+        html += "<th data-type=\"text\">Findings</th><th data-type=\"text\">Tags</th>";
+        html += "</tr></thead>\n<tbody>\n";
+        for entry in &self.results {
+            let class = status_class(entry.status);
+            let size = entry.size.map(|size| size.to_string()).unwrap_or_default();
+            let chain = self
+                .redirect_chain(entry)
+                .iter()
+                .map(|location| escape_html(location))
+                .collect::<Vec<String>>()
+                .join(" &rarr; ");
+            let ttfb = entry
+                .ttfb_ms
+                .map(|ttfb| format!("{:.1}", ttfb))
+                .unwrap_or_default();
+            let total = entry
+                .total_ms
+                .map(|total| format!("{:.1}", total))
+                .unwrap_or_default();
+            let findings = entry
+                .findings
+                .iter()
+                .map(|finding| {
+                    format!(
+                        "{}: <code>{}</code>",
+                        escape_html(&finding.signature),
+                        escape_html(&finding.sample)
+                    )
+                })
+                .collect::<Vec<String>>()
+                .join("<br>");
+            let tags = entry
+                .tags
+                .iter()
+                .map(|tag| escape_html(tag))
+                .collect::<Vec<String>>()
+                .join(", ");
+            let slow = if entry.slow { " slow" } else { "" };
+
+            // Like in the terminal, a page requested with another method than the rest of the
+            // scan has the method shown in front of it.
+            let mut path = self.config.mode.display_name(&entry.path);
+            if entry.method != self.config.method && !entry.method.is_empty() {
+                path = format!("{} {}", entry.method, path);
+            }
+            html += &format!(
+                "<tr class=\"s{}{}\" data-class=\"{}\"><td>{}</td><td>{}</td><td>{}</td><td>{}</td><td>{}</td><td>{}</td><td>{}</td><td>{}</td></tr>\n",
+                &class[..1],
+                slow,
+                class,
+                escape_html(&path),
+                entry.status,
+                size,
+                ttfb,
+                total,
+                chain,
+                findings,
+                tags
+            );
+        }
+        html += "</tbody>\n</table>\n";
+
+        // The findings of the checks run once the scan was done, which the JSON report has too.
+        let bypasses = self.bypasses.iter().map(|bypass| {
+            vec![
+                bypass.original.url.clone(),
+                bypass.variant.name.clone(),
+                format!("{} {}", bypass.variant.method, bypass.url),
+                bypass.original_status.to_string(),
+                bypass.status.to_string(),
+            ]
+        });
+        html += &html_table(
+            "Bypasses",
+            &["Page", "Variant", "Request", "Was", "Status"],
+            bypasses,
+        );
+        let api_endpoints = self.api_endpoints.iter().map(|endpoint| {
+            vec![
+                format!("{} {}", endpoint.operation.method, endpoint.operation.path),
+                endpoint.url.clone(),
+                if endpoint.operation.secured {
+                    "yes"
+                } else {
+                    "no"
+                }
+                .to_string(),
+                endpoint
+                    .status
+                    .map(|status| status.to_string())
+                    .unwrap_or_default(),
+            ]
+        });
+        html += &html_table(
+            "API Endpoints",
+            &["Operation", "Url", "Documented as secured", "Status"],
+            api_endpoints,
+        );
+        let graphql_endpoints = self.graphql_endpoints.iter().map(|endpoint| {
+            vec![
+                endpoint.url.clone(),
+                endpoint.status.to_string(),
+                endpoint.introspection_label(),
+                endpoint.schema_file.clone().unwrap_or_default(),
+            ]
+        });
+        html += &html_table(
+            "GraphQL Endpoints",
+            &["Url", "Status", "Introspection", "Schema file"],
+            graphql_endpoints,
+        );
+
+        html += REPORT_SCRIPT;
+        html += "</body>\n</html>\n";
+        html
+    }
+}
+
+/// Renders a section of the HTML report with a `title` and a table of `rows` under `headers`.
+/// Every cell is escaped. Nothing is rendered if there are no rows.
+fn html_table(title: &str, headers: &[&str], rows: impl Iterator<Item = Vec<String>>) -> String {
+    let mut html = String::new();
+    for row in rows {
+        let cells: String = row
+            .iter()
+            .map(|cell| format!("<td>{}</td>", escape_html(cell)))
+            .collect();
+        html += &format!("<tr>{}</tr>\n", cells);
+    }
+    if html.is_empty() {
+        return html;
+    }
+    let headers: String = headers
+        .iter()
+        .map(|header| format!("<th>{}</th>", header))
+        .collect();
+    format!(
+        "<h2>{}</h2>\n<table>\n<tr>{}</tr>\n{}</table>\n",
+        title, headers, html
+    )
+}
+
+/// Escapes the characters that have special meaning in HTML so user controlled values, like
+/// page names and redirect locations, are displayed as text.
+pub fn escape_html(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => escaped += "&amp;",
+            '<' => escaped += "&lt;",
+            '>' => escaped += "&gt;",
+            '"' => escaped += "&quot;",
+            '\'' => escaped += "&#39;",
+            _ => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+// The colors match the ones used by `Scanner::display_found` in the terminal.
+const REPORT_STYLE: &str = r#"<style>
+body { font-family: sans-serif; margin: 2em; color: #222; }
+table { border-collapse: collapse; margin-bottom: 1.5em; }
+th, td { border: 1px solid #ccc; padding: 4px 10px; text-align: left; }
+#results th { cursor: pointer; background: #eee; }
+.filters { margin-bottom: 0.5em; }
+.s2 td:nth-child(2) { color: green; }
+.s3 td:nth-child(2) { color: blue; }
+.s4 td:nth-child(2), .s5 td:nth-child(2) { color: red; }
+.s1 td:nth-child(2), .so td:nth-child(2) { color: #b8860b; }
+.slow td:nth-child(5) { background: #ffe4b5; font-weight: bold; }
+</style>
+"#;
+
+const REPORT_SCRIPT: &str = r#"<script>
+(function () {
+  var table = document.getElementById("results");
+  var body = table.tBodies[0];
+  var filter = document.getElementById("filter");
+  var statusClass = document.getElementById("class");
+
+  function applyFilters() {
+    var text = filter.value.toLowerCase();
+    var cls = statusClass.value;
+    Array.prototype.forEach.call(body.rows, function (row) {
+      var matchesText = row.cells[0].textContent.toLowerCase().indexOf(text) !== -1;
+      var matchesClass = cls === "" || row.getAttribute("data-class") === cls;
+      row.style.display = matchesText && matchesClass ? "" : "none";
+    });
+  }
+  filter.addEventListener("input", applyFilters);
+  statusClass.addEventListener("change", applyFilters);
+
+  Array.prototype.forEach.call(table.tHead.rows[0].cells, function (header, column) {
+    var ascending = true;
+    header.addEventListener("click", function () {
+      var numeric = header.getAttribute("data-type") === "number";
+      var rows = Array.prototype.slice.call(body.rows);
+      rows.sort(function (a, b) {
+        var x = a.cells[column].textContent;
+        var y = b.cells[column].textContent;
+        var order = numeric ? (Number(x) || 0) - (Number(y) || 0) : x.localeCompare(y);
+        return ascending ? order : -order;
+      });
+      ascending = !ascending;
+      rows.forEach(function (row) { body.appendChild(row); });
+    });
+  });
+})();
+</script>
+"#;
@@ -0,0 +1,91 @@
+//! Saving the raw responses of matched results.
+//!
+//! When a response directory is set, every response that isn't ignored is written to its own file
+//! in the directory, exactly as it came back: the status line, the headers and the body. The file
+//! name is built from the url and a hash of the request, so scanning the same target again writes
+//! the same response to the same file. An index file maps every saved url to the file it is in.
+
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::Path;
+
+/// The name of the index file written to the response directory.
+pub const INDEX_FILE: &str = "index.json";
+
+/// The most characters of a url kept in the name of a saved response file.
+const MAX_NAME_CHARS: usize = 80;
+
+/// A response that was saved to the response directory.
+#[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SavedResponse {
+    // The url that was requested.
+    pub url: String,
+
+    // The `Host` header that was sent instead of the host from the url, for `vhost` scans.
+    pub host: Option<String>,
+
+    // The HTTP status code of the response.
+    pub status: u16,
+
+    // The name of the file the response was saved to, relative to the response directory.
+    pub file: String,
+}
+
+/// Hashes `bytes` with 64 bit FNV-1a. The hash is only used to tell files apart, so it doesn't
+/// need to be cryptographic, but it has to be the same on every run and every platform.
+pub fn fnv1a(bytes: &[u8]) -> u64 {
+    let mut hash: u64 = 0xcbf2_9ce4_8422_2325;
+    for byte in bytes {
+        hash ^= *byte as u64;
+        hash = hash.wrapping_mul(0x0100_0000_01b3);
+    }
+    hash
+}
+
+/// Returns the name of the file the response for `url`, sent with the `Host` header `host`, is
+/// saved to. The readable part of the name is the url without its scheme, with every character
+/// that isn't safe in a file name replaced by '_'. The hash keeps urls that look the same once
+/// they are cleaned up, or that only differ in their `Host` header, in different files.
+pub fn response_file_name(url: &str, host: Option<&str>) -> String {
+    let without_scheme = url.split_once("://").map_or(url, |(_, rest)| rest);
+    let readable = match host {
+        Some(host) => format!("{}_{}", host, without_scheme),
+        None => without_scheme.to_string(),
+    };
+    let readable: String = readable
+        .trim_end_matches('/')
+        .chars()
+        .map(|c| {
+            if c.is_ascii_alphanumeric() || c == '.' || c == '-' {
+                c
+            } else {
+                '_'
+            }
+        })
+        .take(MAX_NAME_CHARS)
+        .collect();
+
+    let key = format!("{}\n{}", url, host.unwrap_or_default());
+    format!("{}-{:016x}.http", readable, fnv1a(key.as_bytes()))
+}
+
+/// Writes a response to the file `file` in `dir`, creating the directory if it doesn't exist.
+/// `head` is the status line and headers, and `body` is written after it as it was received.
+pub fn save_response(dir: &str, file: &str, head: &str, body: &[u8]) -> Result<(), String> {
+    fs::create_dir_all(dir)
+        .map_err(|err| format!("Could not create response directory {}: {}", dir, err))?;
+    let path = Path::new(dir).join(file);
+    let mut contents = head.as_bytes().to_vec();
+    contents.extend_from_slice(body);
+    fs::write(&path, contents)
+        .map_err(|err| format!("Could not save response to {}: {}", path.display(), err))
+}
+
+/// Reads the index file of the response directory `dir`.
+pub fn read_index(dir: &str) -> Result<Vec<SavedResponse>, String> {
+    let path = Path::new(dir).join(INDEX_FILE);
+    let contents = fs::read_to_string(&path)
+        .map_err(|err| format!("Could not read {}: {}", path.display(), err))?;
+    serde_json::from_str(&contents)
+        .map_err(|err| format!("Could not parse {}: {}", path.display(), err))
+}
@@ -0,0 +1,294 @@
+//! The results of a scan.
+//!
+//! Every response that isn't ignored is stored as a `ScanResult`, a record of the request that
+//! was sent and what came back. Results are identified by the request: the method, the full url
+//! and the `Host` header. Requesting the same page with another method or another `Host` header
+//! keeps both results, while sending exactly the same request again replaces the earlier result.
+//! The `ResultStore` keeps the results in the order they arrived and indexes them by status code,
+//! response size and path depth.
+
+use crate::scan::ScanMode;
+use crate::ResponseDetails;
+use reqwest::Url;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::ops::RangeBounds;
+
+/// What identifies a result: the request that was sent.
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+pub struct ResultKey {
+    pub method: String,
+    pub url: String,
+    pub host: Option<String>,
+}
+
+/// A single response that was kept as a result of the scan.
+#[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ScanResult {
+    // The name the result is shown under: the page for `dir` scans, the virtual host name for
+    // `vhost` scans, the word for `fuzz` scans and the parameter for `params` scans.
+    pub name: String,
+
+    // The HTTP method the request was sent with.
+    pub method: String,
+
+    // The full url that was requested.
+    pub url: String,
+
+    // The `Host` header that was sent instead of the host from the url, for `vhost` scans.
+    pub host: Option<String>,
+
+    // The HTTP status code of the response.
+    pub status: u16,
+
+    // Labels shown after the name, like the reasons a parameter was reported.
+    #[serde(default)]
+    pub tags: Vec<String>,
+
+    // Everything else that is known about the response.
+    #[serde(default)]
+    pub details: ResponseDetails,
+}
+
+impl ScanResult {
+    /// Make a new result for a GET request of `url` that got a `status` response, shown as `name`.
+    pub fn new(name: &str, url: &str, status: u16) -> Self {
+        ScanResult {
+            name: name.to_string(),
+            method: "GET".to_string(),
+            url: url.to_string(),
+            status,
+            ..Default::default()
+        }
+    }
+
+    /// Returns the key that identifies the result.
+    pub fn key(&self) -> ResultKey {
+        ResultKey {
+            method: self.method.clone(),
+            url: self.url.clone(),
+            host: self.host.clone(),
+        }
+    }
+
+    /// Returns the size of the response body in bytes, if it is known.
+    pub fn size(&self) -> Option<u64> {
+        self.details.content_length
+    }
+
+    /// Returns how many segments deep the requested path is. The site root is at depth 0, and
+    /// both "/admin" and "/admin/" are at depth 1.
+    pub fn depth(&self) -> usize {
+        let path = match Url::parse(&self.url) {
+            Ok(url) => url.path().to_string(),
+            Err(_) => self.url.clone(),
+        };
+        path.split('/')
+            .filter(|segment| !segment.is_empty())
+            .count()
+    }
+
+    /// Returns the name with the redirect location and tags appended, the way it is shown in the
+    /// terminal and the output file.
+    pub fn label(&self) -> String {
+        let mut label = self.name.clone();
+        if let Some(location) = &self.details.location {
+            match self.status {
+                301 => label += &format!("   [REDIRECTED TO: {}]", location),
+                302 => label += &format!("   [{}]", location),
+                _ => {}
+            }
+        }
+        if !self.tags.is_empty() {
+            label += &format!("   [{}]", self.tags.join(", "));
+        }
+        label
+    }
+
+    /// Returns the label as it is displayed for a `mode` scan.
+    pub fn display_name(&self, mode: ScanMode) -> String {
+        mode.display_name(&self.label())
+    }
+}
+
+/// Every result of a scan, in the order they arrived, indexed by key, status code, size and depth.
+#[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(from = "Vec<ScanResult>", into = "Vec<ScanResult>")]
+pub struct ResultStore {
+    records: Vec<ScanResult>,
+
+    // The position in `records` of every result, by key, status code, size and depth.
+    by_key: BTreeMap<ResultKey, usize>,
+    by_status: BTreeMap<u16, Vec<usize>>,
+    by_size: BTreeMap<Option<u64>, Vec<usize>>,
+    by_depth: BTreeMap<usize, Vec<usize>>,
+}
+
+impl ResultStore {
+    /// Make a new empty result store.
+    pub fn new() -> Self {
+        ResultStore::default()
+    }
+
+    /// Returns the number of results.
+    pub fn len(&self) -> usize {
+        self.records.len()
+    }
+
+    /// Returns true if there are no results.
+    pub fn is_empty(&self) -> bool {
+        self.records.is_empty()
+    }
+
+    /// Stores `result`. A result with the same key is replaced, but keeps its place in the order.
+    pub fn insert(&mut self, result: ScanResult) {
+        match self.by_key.get(&result.key()) {
+            Some(&position) => {
+                self.unindex(position);
+                self.records[position] = result;
+                self.index(position);
+            }
+            None => {
+                self.records.push(result);
+                self.index(self.records.len() - 1);
+            }
+        }
+    }
+
+    /// Returns the result for the request `key`.
+    pub fn get(&self, key: &ResultKey) -> Option<&ScanResult> {
+        self.by_key
+            .get(key)
+            .map(|&position| &self.records[position])
+    }
+
+    /// Returns the first result shown as `name`.
+    pub fn find(&self, name: &str) -> Option<&ScanResult> {
+        self.records.iter().find(|result| result.name == name)
+    }
+
+    /// Returns every result in the order they arrived.
+    pub fn iter(&self) -> std::slice::Iter<'_, ScanResult> {
+        self.records.iter()
+    }
+
+    /// Returns every result sorted by status code in ascending order. Results with the same
+    /// status code are sorted by name, then by key.
+    pub fn sorted(&self) -> Vec<&ScanResult> {
+        let mut sorted: Vec<&ScanResult> = self.records.iter().collect();
+        sorted.sort_by(|a, b| (a.status, &a.name, a.key()).cmp(&(b.status, &b.name, b.key())));
+        sorted
+    }
+
+    /// Returns the results with a status code in `statuses`, in the order they arrived.
+    pub fn with_status(&self, statuses: impl RangeBounds<u16>) -> Vec<&ScanResult> {
+        self.lookup(
+            self.by_status
+                .range(statuses)
+                .map(|(_, positions)| positions),
+        )
+    }
+
+    /// Returns the results with a known size in `sizes`, in the order they arrived.
+    pub fn with_size(&self, sizes: impl RangeBounds<u64>) -> Vec<&ScanResult> {
+        self.lookup(
+            self.by_size
+                .iter()
+                .filter(|(size, _)| size.is_some_and(|size| sizes.contains(&size)))
+                .map(|(_, positions)| positions),
+        )
+    }
+
+    /// Returns the results whose path is `depth` segments deep, in the order they arrived.
+    pub fn at_depth(&self, depth: usize) -> Vec<&ScanResult> {
+        self.lookup(self.by_depth.get(&depth))
+    }
+
+    /// Counts the results with each status code.
+    pub fn status_counts(&self) -> BTreeMap<u16, usize> {
+        self.by_status
+            .iter()
+            .map(|(status, positions)| (*status, positions.len()))
+            .collect()
+    }
+
+    /// Keeps only the results `keep` returns true for.
+    pub fn retain(&mut self, keep: impl FnMut(&ScanResult) -> bool) {
+        let mut records = std::mem::take(&mut self.records);
+        records.retain(keep);
+        *self = ResultStore::from(records);
+    }
+
+    /// Returns the results at the `positions` from one or more index entries, in arrival order.
+    fn lookup<'a>(&self, positions: impl IntoIterator<Item = &'a Vec<usize>>) -> Vec<&ScanResult> {
+        let mut positions: Vec<usize> = positions.into_iter().flatten().copied().collect();
+        positions.sort_unstable();
+        positions
+            .into_iter()
+            .map(|position| &self.records[position])
+            .collect()
+    }
+
+    /// Adds the result at `position` to every index.
+    fn index(&mut self, position: usize) {
+        let result = &self.records[position];
+        self.by_key.insert(result.key(), position);
+        self.by_status
+            .entry(result.status)
+            .or_default()
+            .push(position);
+        self.by_size
+            .entry(result.size())
+            .or_default()
+            .push(position);
+        self.by_depth
+            .entry(result.depth())
+            .or_default()
+            .push(position);
+    }
+
+    /// Removes the result at `position` from every index.
+    fn unindex(&mut self, position: usize) {
+        let result = &self.records[position];
+        self.by_key.remove(&result.key());
+        let (status, size, depth) = (result.status, result.size(), result.depth());
+        remove_position(&mut self.by_status, status, position);
+        remove_position(&mut self.by_size, size, position);
+        remove_position(&mut self.by_depth, depth, position);
+    }
+}
+
+/// Removes `position` from the entry for `value` in an index, and the entry once it is empty.
+fn remove_position<T: Ord>(index: &mut BTreeMap<T, Vec<usize>>, value: T, position: usize) {
+    if let Some(positions) = index.get_mut(&value) {
+        positions.retain(|&p| p != position);
+        if positions.is_empty() {
+            index.remove(&value);
+        }
+    }
+}
+
+impl From<Vec<ScanResult>> for ResultStore {
+    fn from(results: Vec<ScanResult>) -> Self {
+        let mut store = ResultStore::new();
+        for result in results {
+            store.insert(result);
+        }
+        store
+    }
+}
+
+impl From<ResultStore> for Vec<ScanResult> {
+    fn from(store: ResultStore) -> Self {
+        store.records
+    }
+}
+
+impl<'a> IntoIterator for &'a ResultStore {
+    type Item = &'a ScanResult;
+    type IntoIter = std::slice::Iter<'a, ScanResult>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.records.iter()
+    }
+}
@@ -0,0 +1,74 @@
+//! Retrying requests that failed for reasons that are likely to go away.
+//!
+//! Timeouts, refused or reset connections and gateway errors (502, 503 and 504) are usually caused
+//! by an overloaded server or a flaky network rather than by the page itself. A `RetryPolicy`
+//! decides which of these are retried, how many times, and how long to wait between attempts.
+
+use serde::{Deserialize, Serialize};
+use std::time::Duration;
+
+/// The number of times a request is sent before giving up, when none is configured.
+pub const DEFAULT_MAX_ATTEMPTS: u32 = 3;
+
+/// How long to wait before the first retry, when none is configured.
+pub const DEFAULT_BACKOFF_MS: u64 = 250;
+
+/// The longest time to wait between two attempts, no matter how many attempts were made.
+pub const MAX_BACKOFF_MS: u64 = 10_000;
+
+/// The HTTP status codes that are retried when none are configured.
+pub const DEFAULT_RETRY_STATUSES: [u16; 3] = [502, 503, 504];
+
+/// Which failures are retried and how long to wait between attempts.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RetryPolicy {
+    // The total number of times a request is sent, including the first attempt.
+    pub max_attempts: u32,
+
+    // How long to wait before the first retry. The wait doubles after every attempt.
+    pub backoff_ms: u64,
+
+    // Responses with these status codes are retried.
+    pub retry_statuses: Vec<u16>,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        RetryPolicy {
+            max_attempts: DEFAULT_MAX_ATTEMPTS,
+            backoff_ms: DEFAULT_BACKOFF_MS,
+            retry_statuses: DEFAULT_RETRY_STATUSES.to_vec(),
+        }
+    }
+}
+
+impl RetryPolicy {
+    /// Returns true if another attempt is allowed after `attempt` attempts were already made.
+    pub fn can_retry(&self, attempt: u32) -> bool {
+        attempt < self.max_attempts
+    }
+
+    /// Returns how long to wait after the `attempt`th attempt failed. The first retry waits
+    /// `backoff_ms`, and every retry after that waits twice as long as the one before it, up to
+    /// `MAX_BACKOFF_MS`.
+    pub fn backoff(&self, attempt: u32) -> Duration {
+        let multiplier = 2u64.saturating_pow(attempt.saturating_sub(1));
+        Duration::from_millis(
+            self.backoff_ms
+                .saturating_mul(multiplier)
+                .min(MAX_BACKOFF_MS),
+        )
+    }
+
+    /// Returns true if a response with the HTTP status code `status` should be retried.
+    pub fn should_retry_status(&self, status: u16) -> bool {
+        self.retry_statuses.contains(&status)
+    }
+
+    /// Returns true if a request that failed with `err` should be retried. Timeouts and connection
+    /// problems, like a refused or reset connection, are retried. Errors in building the request,
+    /// like an invalid url, will never succeed so they aren't.
+    pub fn should_retry_error(&self, err: &reqwest::Error) -> bool {
+        err.is_timeout() || err.is_connect() || err.is_request() || err.is_body()
+    }
+}
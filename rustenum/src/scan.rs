@@ -0,0 +1,766 @@
+//! The scan engine shared by the `dir`, `vhost` and `fuzz` scan modes.
+//!
+//! Each mode turns the words of the `Scanner`'s wordlist into a list of `Probe`s. The probes are
+//! all sent the same way by `Scanner::run`, and every response that isn't ignored is stored as a
+//! `ScanResult` in `Scanner::found`.
+
+use crate::audit::{now_ms, AuditEntry, AuditLog};
+use crate::config::MAX_THREADS;
+use crate::plugins::{
+    parse_headers, Analysis, Classification, PluginResponse, MAX_FOLLOW_UP_ROUNDS,
+};
+use crate::responses::{response_file_name, save_response, SavedResponse};
+use crate::results::ScanResult;
+use crate::retry::RetryPolicy;
+use crate::scope::{SkipReason, SkippedRequest};
+use crate::target::Target;
+use crate::{is_ignored, ResponseDetails, Scanner};
+use futures::{stream, StreamExt};
+use indicatif::ProgressBar;
+use reqwest::header::HeaderMap;
+use reqwest::{Client, RequestBuilder};
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use std::time::{Duration, Instant};
+
+/// The keyword that is replaced by each word in the wordlist in `fuzz` mode.
+pub const FUZZ_KEYWORD: &str = "FUZZ";
+
+/// The most bytes of a response body that are kept when the body is needed. The rest of the body
+/// is still read, so its size and the total time are known, but it isn't held in memory.
+pub const MAX_BODY_BYTES: usize = 10 * 1024 * 1024;
+
+/// The kind of scan a `Scanner` runs. This decides how words are turned into requests and how
+/// the stored results are displayed.
+#[derive(Default, Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ScanMode {
+    // Each word is a page or directory appended to the base url.
+    #[default]
+    Dir,
+
+    // Each word is a subdomain sent in the `Host` header to the base url.
+    Vhost,
+
+    // Each word replaces the `FUZZ` keyword anywhere in the url.
+    Fuzz,
+
+    // Each word is a candidate parameter name sent to the url.
+    Params,
+}
+
+impl ScanMode {
+    /// Returns how a stored result name is displayed. Pages get a leading '/', unless they are
+    /// named by their full url like in a scan of several targets, while virtual host names and
+    /// fuzzed words are displayed as they are.
+    pub fn display_name(&self, name: &str) -> String {
+        match self {
+            ScanMode::Dir if name.contains("://") => name.to_string(),
+            ScanMode::Dir => format!("/{}", name),
+            ScanMode::Vhost | ScanMode::Fuzz | ScanMode::Params => name.to_string(),
+        }
+    }
+}
+
+/// A single request sent by the scan engine.
+#[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Probe {
+    // The name the result is shown under in `Scanner::found`.
+    pub name: String,
+
+    // The full url to request.
+    pub url: String,
+
+    // A `Host` header to send instead of the host from the url.
+    pub host: Option<String>,
+
+    // The word from the wordlist the probe was built from.
+    pub word: String,
+
+    // Form fields sent url encoded in the body. Probes with a form are sent as a POST instead
+    // of a GET.
+    pub form: Option<Vec<(String, String)>>,
+
+    // The HTTP method to send the probe with, like the method an API operation is documented
+    // under. The method is chosen by the body when this isn't set.
+    #[serde(default)]
+    pub method: Option<String>,
+
+    // A JSON document sent as the body, like a GraphQL query. Probes with a JSON body are sent as
+    // a POST instead of a GET.
+    #[serde(default)]
+    pub json: Option<String>,
+}
+
+impl Probe {
+    /// Returns the HTTP method the probe is sent with. Probes with a form or a JSON body are sent
+    /// as a POST unless another method is set.
+    pub fn method(&self) -> &str {
+        match &self.method {
+            Some(method) => method,
+            None if self.form.is_some() || self.json.is_some() => "POST",
+            None => "GET",
+        }
+    }
+}
+
+/// What happened to a single probe: the result, if it got a response, and the word of the probe
+/// if it never did.
+pub(crate) struct ProbeOutcome {
+    pub(crate) result: Option<ScanResult>,
+    pub(crate) unreachable: Option<String>,
+
+    // The status line and headers of the response, if the body was asked for.
+    pub(crate) head: Option<String>,
+
+    // The response body as it was received, if it was asked for.
+    pub(crate) body: Option<Vec<u8>>,
+
+    // Where the response was saved, if it was written to the response directory.
+    pub(crate) saved: Option<SavedResponse>,
+
+    // Whether the plugins want the result kept or ignored, whatever its status code.
+    pub(crate) classification: Option<Classification>,
+
+    // The paths the plugins asked to be requested after the wordlist.
+    pub(crate) enqueue: Vec<String>,
+}
+
+impl Scanner {
+    /// Builds one probe for each word in the wordlist that requests the word as a page of the
+    /// base url. The word is stored under its own name. No probes are built if the site hasn't
+    /// been set. Words with a "." or ".." segment would leave the base path, so they are stored in
+    /// `skipped` instead.
+    pub fn dir_probes(&mut self) -> Vec<Probe> {
+        let target = match Target::parse(&self.site) {
+            Ok(target) => target,
+            Err(_) => return Vec::new(),
+        };
+        let mut probes = Vec::new();
+        for word in self.wordlist.0.clone() {
+            // The word is joined onto the base path, before the query string of the site.
+            let url = match target.join(&word) {
+                Ok(url) => url,
+                Err(url) => {
+                    self.skip_dot_segment(url);
+                    continue;
+                }
+            };
+            probes.push(Probe {
+                name: word.clone(),
+                url,
+                host: None,
+                word,
+                form: None,
+                method: None,
+                json: None,
+            });
+        }
+        probes
+    }
+
+    /// Builds one probe for each word in the wordlist on every one of the `targets`, the way
+    /// `dir_probes` does for the site. Each result is named by its full url, since the same page
+    /// can be found on several targets. Returns an error if a target can't be parsed.
+    pub fn target_probes(&mut self, targets: &[String]) -> Result<Vec<Probe>, String> {
+        let mut probes = Vec::new();
+        for target in targets {
+            let target = Target::parse(target)?;
+            for word in self.wordlist.0.clone() {
+                let url = match target.join(&word) {
+                    Ok(url) => url,
+                    Err(url) => {
+                        self.skip_dot_segment(url);
+                        continue;
+                    }
+                };
+                probes.push(Probe {
+                    name: url.clone(),
+                    url,
+                    host: None,
+                    word,
+                    form: None,
+                    method: None,
+                    json: None,
+                });
+            }
+        }
+        Ok(probes)
+    }
+
+    /// Stores the `url` of a word with a dot segment in `skipped`.
+    fn skip_dot_segment(&mut self, url: String) {
+        self.skipped.push(SkippedRequest {
+            url,
+            reason: SkipReason::DotSegment,
+        });
+    }
+
+    /// Builds one probe for each word in the wordlist that requests the base url with a `Host`
+    /// header of `<word>.<domain>`. The result is stored under the full virtual host name.
+    pub fn vhost_probes(&self, domain: &str) -> Vec<Probe> {
+        self.wordlist
+            .0
+            .iter()
+            .map(|word| {
+                let host = format!("{}.{}", word, domain.trim_start_matches('.'));
+                Probe {
+                    name: host.clone(),
+                    url: self.site.to_string(),
+                    host: Some(host),
+                    word: word.clone(),
+                    form: None,
+                    method: None,
+                    json: None,
+                }
+            })
+            .collect()
+    }
+
+    /// Builds one probe for each word in the wordlist by replacing every `FUZZ` keyword in the
+    /// base url with the word. The result is stored under the word.
+    pub fn fuzz_probes(&self) -> Vec<Probe> {
+        self.wordlist
+            .0
+            .iter()
+            .map(|word| Probe {
+                name: word.clone(),
+                url: self.site.replace(FUZZ_KEYWORD, word),
+                host: None,
+                word: word.clone(),
+                form: None,
+                method: None,
+                json: None,
+            })
+            .collect()
+    }
+
+    /// Builds the probes for the scanner's `mode`. `vhost` mode needs the `domain` the words are
+    /// prepended to, the other modes ignore it. `params` scans build their probes as they go in
+    /// `discover_params`, so none are returned for them.
+    pub fn probes(&mut self, domain: &str) -> Vec<Probe> {
+        match self.mode {
+            ScanMode::Dir => self.dir_probes(),
+            ScanMode::Vhost => self.vhost_probes(domain),
+            ScanMode::Fuzz => self.fuzz_probes(),
+            ScanMode::Params => Vec::new(),
+        }
+    }
+
+    /// Sends a request for every probe with the probe's own method, with at most `thread_count`
+    /// requests in flight at once, and stores the results. The `progress_bar` is advanced each time
+    /// a probe is answered.
+    ///
+    /// Probes that are out of the scanner's scope, or over its request budget, are never sent and
+    /// are stored in `skipped` instead.
+    ///
+    /// Requests that time out, fail to connect, or get a status code from the retry policy are
+    /// sent again after a backoff. The words of probes that never got a response, or still got a
+    /// status code from the retry policy on the last attempt, are stored in `unreachable`.
+    ///
+    /// Every response is given to the scanner's plugins. The paths they enqueue are requested once
+    /// the probes are done, for up to `MAX_FOLLOW_UP_ROUNDS` rounds, and each url is only
+    /// requested once.
+    pub async fn run(
+        &mut self,
+        client: &Client,
+        probes: Vec<Probe>,
+        thread_count: usize,
+        progress_bar: &ProgressBar,
+    ) {
+        let mut requested: HashSet<String> = probes.iter().map(|probe| probe.url.clone()).collect();
+        let mut enqueued = self
+            .run_round(client, probes, thread_count, progress_bar)
+            .await;
+        for _ in 0..MAX_FOLLOW_UP_ROUNDS {
+            let probes = self.follow_up_probes(&enqueued, &mut requested);
+            let stopped = self
+                .control
+                .as_ref()
+                .is_some_and(|control| control.is_stopped());
+            if probes.is_empty() || stopped {
+                break;
+            }
+            progress_bar.inc_length(probes.len() as u64);
+            if let Some(control) = &self.control {
+                control.add_to_total(probes.len());
+            }
+            enqueued = self
+                .run_round(client, probes, thread_count, progress_bar)
+                .await;
+        }
+    }
+
+    /// Builds a probe for every path the plugins enqueued that hasn't been `requested` yet. Paths
+    /// are requested under the base url like words of the wordlist, and only in `dir` scans.
+    fn follow_up_probes(
+        &mut self,
+        paths: &[String],
+        requested: &mut HashSet<String>,
+    ) -> Vec<Probe> {
+        let target = match (self.mode, Target::parse(&self.site)) {
+            (ScanMode::Dir, Ok(target)) => target,
+            _ => return Vec::new(),
+        };
+        let mut probes = Vec::new();
+        for path in paths {
+            let word = path.trim_start_matches('/');
+            let url = match target.join(word) {
+                Ok(url) => url,
+                Err(url) => {
+                    self.skip_dot_segment(url);
+                    continue;
+                }
+            };
+            if !word.is_empty() && requested.insert(url.clone()) {
+                probes.push(Probe {
+                    name: word.to_string(),
+                    url,
+                    host: None,
+                    word: word.to_string(),
+                    form: None,
+                    method: None,
+                    json: None,
+                });
+            }
+        }
+        probes
+    }
+
+    /// Sends one round of probes for `run`, and returns the paths the plugins enqueued.
+    async fn run_round(
+        &mut self,
+        client: &Client,
+        probes: Vec<Probe>,
+        thread_count: usize,
+        progress_bar: &ProgressBar,
+    ) -> Vec<String> {
+        // Nothing out of scope or over the request budget is ever sent.
+        let candidates = probes.len();
+        let probes = self.scoped_probes(probes);
+        let skipped = (candidates - probes.len()) as u64;
+        if skipped > 0 {
+            if let Some(length) = progress_bar.length() {
+                progress_bar.set_length(length.saturating_sub(skipped));
+            }
+            if let Some(control) = &self.control {
+                control.remove_from_total(skipped as usize);
+            }
+        }
+
+        // The results are stored while requests are still in flight, so the requests get their
+        // own copies of the settings they need instead of borrowing the scanner.
+        let policy = &self.retry_policy.clone();
+        let signatures = &self.signatures.clone();
+        let response_dir = self.response_dir.clone();
+        let response_dir = response_dir.as_deref();
+        let ignore_list = &self.status_code_ignore_list.clone();
+        let match_list = &self.status_code_match_list.clone();
+        let plugins = &self.plugins.clone();
+        let keep_body = signatures.is_some() || response_dir.is_some() || !plugins.is_empty();
+        let audit = self.audit.clone();
+        let audit = audit.as_deref();
+
+        // With a dashboard, every request waits for the `ScanControl` to let it through, so the
+        // stream is allowed as many requests as the dashboard could ever ask for.
+        let control = self.control.clone();
+        let control = control.as_deref();
+        let buffer_size = match control {
+            Some(_) => thread_count.max(MAX_THREADS),
+            None => thread_count,
+        };
+
+        // Hits are written to the output file as they arrive, so the results found so far are
+        // kept even if the scan never finishes.
+        let mut live_output = self.open_live_output(progress_bar);
+
+        // The code immediately below assigning the stream of ProbeOutcomes to `temp` is mostly
+        // taken from a Stack Overflow post by user Shepmaster. The url to the post/solution
+        // is in the README.
+
+        // I am creating an iterator over the probes then turning it into a stream iterator.
+        let mut temp = stream::iter(probes)
+            // Each probe in the stream iterator is then mapped to the block of code that scans a web page.
+            .map(|probe| async move {
+                // Wait until the dashboard lets the request through. The probe is skipped if the
+                // scan is stopped first.
+                let _permit = match control {
+                    Some(control) => match control.acquire().await {
+                        Some(permit) => Some(permit),
+                        None => return skipped_outcome(),
+                    },
+                    None => None,
+                };
+
+                // The body is only kept long enough to check it against the signatures and to
+                // save it, so the bodies of a whole scan are never held in memory at once.
+                let (url, host) = (probe.url.clone(), probe.host.clone());
+                let mut outcome = send_probe(client, probe, policy, keep_body, audit).await;
+                let (head, body) = (outcome.head.take(), outcome.body.take());
+                let result = match outcome.result.as_mut() {
+                    Some(result) => result,
+                    None => {
+                        progress_bar.inc(1);
+                        return outcome;
+                    }
+                };
+                if let (Some(signatures), Some(body)) = (signatures.as_ref(), &body) {
+                    result.details.findings = signatures.scan(&String::from_utf8_lossy(body));
+                }
+
+                // Every plugin gets to annotate and classify the response before it is stored.
+                if let (false, Some(head), Some(body)) = (plugins.is_empty(), &head, &body) {
+                    let response = PluginResponse {
+                        url: url.clone(),
+                        method: result.method.clone(),
+                        status: result.status,
+                        headers: parse_headers(head),
+                        body: body.clone(),
+                    };
+                    // Plugins may block, like a program that is slow to answer, so they run off
+                    // the threads that send the requests.
+                    let set = plugins.clone();
+                    let (analysis, errors) =
+                        tokio::task::spawn_blocking(move || set.analyze(&response))
+                            .await
+                            .unwrap_or_else(|err| {
+                                (
+                                    Analysis::default(),
+                                    vec![format!("A plugin failed: {}", err)],
+                                )
+                            });
+                    for err in errors {
+                        if let Some(control) = control {
+                            control.record_error(err.clone());
+                        }
+                        progress_bar.println(err);
+                    }
+                    result.tags.extend(analysis.tags);
+                    outcome.classification = analysis.classification;
+                    outcome.enqueue = analysis.enqueue;
+                }
+
+                // Only the responses that end up in the results are saved.
+                if let (Some(dir), Some(head), Some(body)) = (response_dir, head, body) {
+                    let status = result.status;
+                    let kept = match outcome.classification {
+                        Some(classification) => classification == Classification::Keep,
+                        None => {
+                            !is_ignored(ignore_list, match_list, status)
+                                && !control.is_some_and(|control| control.is_ignored(status))
+                        }
+                    };
+                    if kept {
+                        let file = response_file_name(&url, host.as_deref());
+                        match save_response(dir, &file, &head, &body) {
+                            Ok(()) => {
+                                result.details.saved_as = Some(file.clone());
+                                outcome.saved = Some(SavedResponse {
+                                    url,
+                                    host,
+                                    status,
+                                    file,
+                                });
+                            }
+                            Err(err) => {
+                                if let Some(control) = control {
+                                    control.record_error(err.clone());
+                                }
+                                progress_bar.println(err);
+                            }
+                        }
+                    }
+                }
+
+                // update the progress_bar now that another probe has been answered.
+                progress_bar.inc(1);
+                outcome
+            })
+            // buffer_unordered caps the number of parallel/concurrent requests being sent to 'buffer_size'.
+            // This should prevent DoS for a website by preventing every request from being sent at once. The default limit is
+            // DEFAULT_THREADS requests at a time, but it can be changed to any value from 1 to MAX_THREADS. With a dashboard
+            // the buffer is MAX_THREADS wide and the `ScanControl` holds the requests back to the chosen concurrency.
+            // I am using buffer_unordered because I do not care about the order of my responses. I only care that they arrived.
+            // When a response is received, then a new request is added to the buffer_unordered queue.
+            .buffer_unordered(buffer_size);
+
+        // Process every response the moment it arrives.
+        // `add_to_found()` ignores any result that has
+        // a status code in the scanner's ignore list, unless a plugin classified it,
+        // but the response time of every response is kept as a baseline for finding slow pages.
+        let mut enqueued = Vec::new();
+        while let Some(outcome) = temp.next().await {
+            if let Some(control) = control {
+                // Status codes ignored from the dashboard also remove the results already found.
+                let ignores = control.take_ignores();
+                if !ignores.is_empty() {
+                    self.add_to_ignore_list(&ignores);
+                    self.found
+                        .retain(|result| !ignores.contains(&result.status));
+                }
+                if control.is_stopped() {
+                    break;
+                }
+                control.record_response(outcome.result.as_ref().map(|result| result.status));
+                if let Some(word) = &outcome.unreachable {
+                    control.record_error(format!(
+                        "{}: no usable response after {} attempts",
+                        word, policy.max_attempts
+                    ));
+                }
+            }
+            if let Some(word) = outcome.unreachable {
+                self.unreachable.push(word);
+            }
+            if let Some(saved) = outcome.saved {
+                self.saved_responses.push(saved);
+            }
+            if let Some(result) = outcome.result {
+                if let Some(total_ms) = result.details.total_ms {
+                    self.latencies.push(total_ms);
+                }
+                let stored = match outcome.classification {
+                    Some(Classification::Keep) => {
+                        self.found.insert(result.clone());
+                        true
+                    }
+                    Some(Classification::Ignore) => false,
+                    None => self.add_to_found(result.clone()),
+                };
+                if stored {
+                    self.report_hit(&result, progress_bar, &mut live_output);
+                }
+            }
+            enqueued.extend(outcome.enqueue);
+        }
+        enqueued
+    }
+}
+
+/// The outcome of a probe that was never sent because the scan was stopped.
+fn skipped_outcome() -> ProbeOutcome {
+    ProbeOutcome {
+        result: None,
+        unreachable: None,
+        head: None,
+        body: None,
+        saved: None,
+        classification: None,
+        enqueue: Vec::new(),
+    }
+}
+
+/// Sends a single probe with `client`, retrying it as allowed by `policy`, and returns what
+/// happened to it. The response body is only kept when `keep_body` is true, since most scans
+/// only need its size. Every attempt is written to the `audit` log, if there is one.
+pub(crate) async fn send_probe(
+    client: &Client,
+    probe: Probe,
+    policy: &RetryPolicy,
+    keep_body: bool,
+    audit: Option<&AuditLog>,
+) -> ProbeOutcome {
+    let method = probe.method().to_string();
+    let mut attempt = 1;
+    loop {
+        // Probes with a body are sent as a POST, everything else as a GET, unless the probe has a
+        // method of its own.
+        let http_method = reqwest::Method::from_bytes(method.as_bytes()).unwrap_or_default();
+        let mut request = client.request(http_method, &probe.url);
+        if let Some(form) = &probe.form {
+            request = request.form(form);
+        }
+        if let Some(json) = &probe.json {
+            request = request
+                .header(reqwest::header::CONTENT_TYPE, "application/json")
+                .body(json.clone());
+        }
+        if let Some(host) = &probe.host {
+            request = request.header(reqwest::header::HOST, host);
+        }
+
+        // What is about to be sent is noted down for the audit log before the request is
+        // sent, and the entry is written once the outcome of the attempt is known.
+        let entry = audit.map(|audit| audit_entry(audit, &request, attempt));
+        let record = |status: Option<u16>, bytes: Option<u64>, error: Option<String>| {
+            if let (Some(audit), Some(entry)) = (audit, entry.clone()) {
+                audit.record(&AuditEntry {
+                    status,
+                    bytes,
+                    error,
+                    ..entry
+                });
+            }
+        };
+
+        // Send the request for the url and await a response. The time until the
+        // headers arrive and the time until the whole body is read are both recorded.
+        let sent = Instant::now();
+        match request.send().await {
+            // The server is overloaded or a gateway in front of it failed. Wait and
+            // try again, unless this was the last attempt.
+            Ok(resp)
+                if policy.should_retry_status(resp.status().as_u16())
+                    && policy.can_retry(attempt) =>
+            {
+                record(Some(resp.status().as_u16()), None, None);
+            }
+
+            // The response is recorded as a result of the request. Whether it is kept is up to
+            // the scanner's ignore list.
+            Ok(mut resp) => {
+                let ttfb = sent.elapsed();
+                let mut details = response_details(&resp);
+
+                // Read the body so the total time covers the whole response. The size of
+                // the body is used when the server didn't send a `Content-Length` header.
+                // It is read a chunk at a time, and at most `MAX_BODY_BYTES` of it are kept.
+                let head = keep_body.then(|| response_head(&resp));
+                let status = resp.status().as_u16();
+                let mut kept_body = keep_body.then(Vec::new);
+                let mut bytes = Some(0);
+                loop {
+                    match resp.chunk().await {
+                        Ok(Some(chunk)) => {
+                            bytes = bytes.map(|bytes| bytes + chunk.len() as u64);
+                            if let Some(kept) = &mut kept_body {
+                                let room = MAX_BODY_BYTES.saturating_sub(kept.len());
+                                kept.extend_from_slice(&chunk[..chunk.len().min(room)]);
+                            }
+                        }
+                        Ok(None) => break,
+                        Err(_) => {
+                            bytes = None;
+                            break;
+                        }
+                    }
+                }
+                if bytes.is_some() {
+                    details.content_length = bytes;
+                } else {
+                    kept_body = None;
+                }
+                record(Some(status), bytes, None);
+                details.ttfb_ms = Some(as_millis(ttfb));
+                details.total_ms = Some(as_millis(sent.elapsed()));
+                // A status code from the retry policy on the last attempt is still stored as a
+                // result, but the word is also remembered to be scanned again later.
+                let unreachable = policy.should_retry_status(status).then_some(probe.word);
+                let result = ScanResult {
+                    name: probe.name,
+                    method,
+                    url: probe.url,
+                    host: probe.host,
+                    status,
+                    tags: Vec::new(),
+                    details,
+                };
+                return ProbeOutcome {
+                    result: Some(result),
+                    unreachable,
+                    head,
+                    body: kept_body,
+                    saved: None,
+                    classification: None,
+                    enqueue: Vec::new(),
+                };
+            }
+
+            // A timeout or a dropped connection might work on the next attempt.
+            Err(err) if policy.should_retry_error(&err) && policy.can_retry(attempt) => {
+                record(None, None, Some(err.to_string()));
+            }
+
+            // Every attempt failed, so the word is remembered to be scanned again later.
+            Err(err) => {
+                record(None, None, Some(err.to_string()));
+                return ProbeOutcome {
+                    result: None,
+                    unreachable: Some(probe.word),
+                    head: None,
+                    body: None,
+                    saved: None,
+                    classification: None,
+                    enqueue: Vec::new(),
+                };
+            }
+        }
+
+        tokio::time::sleep(policy.backoff(attempt)).await;
+        attempt += 1;
+    }
+}
+
+/// Starts the audit log entry for an attempt at sending `request`. The status code and size are
+/// filled in once the response arrives.
+fn audit_entry(audit: &AuditLog, request: &RequestBuilder, attempt: u32) -> AuditEntry {
+    // A copy of the request is built to see the exact url and headers that will be sent.
+    let built = request.try_clone().and_then(|request| request.build().ok());
+    let (method, url, headers_hash) = match &built {
+        Some(built) => (
+            built.method().to_string(),
+            built.url().to_string(),
+            audit.headers_hash(built.headers()),
+        ),
+        None => (
+            String::new(),
+            String::new(),
+            audit.headers_hash(&HeaderMap::new()),
+        ),
+    };
+    AuditEntry {
+        timestamp_ms: now_ms(),
+        method,
+        url,
+        headers_hash,
+        attempt,
+        ..Default::default()
+    }
+}
+
+/// This method processes a reqwest::Response object received from the Client.get() in the scan.
+/// This method takes 'ext'(webpage that was requested) and 'resp' as arguments, and returns the
+/// page the way it is displayed, with the location of a 301 or 302 redirect, and the status code.
+pub fn process_response(ext: String, resp: &reqwest::Response) -> (String, u16) {
+    let result = ScanResult {
+        name: ext,
+        status: resp.status().as_u16(),
+        details: response_details(resp),
+        ..Default::default()
+    };
+    (result.label(), result.status)
+}
+
+/// This method collects the parts of a reqwest::Response that are kept with its result, like
+/// the redirect location and the size of the body.
+pub fn response_details(resp: &reqwest::Response) -> ResponseDetails {
+    ResponseDetails {
+        location: resp
+            .headers()
+            .get(reqwest::header::LOCATION)
+            .and_then(|location| location.to_str().ok())
+            .map(|location| location.to_string()),
+        content_length: resp.content_length(),
+        ..Default::default()
+    }
+}
+
+/// Rebuilds the status line and headers of a response the way they were sent, ending with the
+/// blank line that separates them from the body.
+pub fn response_head(resp: &reqwest::Response) -> String {
+    let mut head = format!("{:?} {}\r\n", resp.version(), resp.status());
+    for (name, value) in resp.headers() {
+        head += &format!(
+            "{}: {}\r\n",
+            name,
+            String::from_utf8_lossy(value.as_bytes())
+        );
+    }
+    head += "\r\n";
+    head
+}
+
+/// Converts a duration into fractional milliseconds.
+fn as_millis(duration: Duration) -> f64 {
+    duration.as_secs_f64() * 1000.0
+}
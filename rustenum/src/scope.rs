@@ -0,0 +1,210 @@
+//! Scope control for the requests a scan sends.
+//!
+//! A `Scope` decides which paths a scan is allowed to request. Paths can be limited to the ones
+//! matching an include pattern, paths matching an exclude pattern are never requested, and paths
+//! that look like they do something destructive, like `/logout` or `/delete`, are skipped unless
+//! they are allowed. A scan can also be given a budget, the most requests it may send. Every
+//! candidate that is skipped is kept with the reason it was skipped, so it can be audited later.
+
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use std::fmt;
+
+/// Path segments that are skipped by default, since requesting them can end a session or change
+/// data on the target.
+pub const DEFAULT_DANGEROUS: [&str; 12] = [
+    "logout",
+    "logoff",
+    "log-out",
+    "signout",
+    "sign-out",
+    "delete",
+    "destroy",
+    "remove",
+    "deactivate",
+    "unsubscribe",
+    "shutdown",
+    "reboot",
+];
+
+/// The prefix that marks a path pattern as a regular expression instead of a glob.
+pub const REGEX_PREFIX: &str = "re:";
+
+/// A pattern that paths are matched against.
+#[derive(Debug, Clone)]
+pub struct PathPattern {
+    // The pattern as it was written.
+    source: String,
+
+    // The compiled pattern. Globs are turned into an anchored regular expression.
+    pattern: Regex,
+}
+
+impl PartialEq for PathPattern {
+    fn eq(&self, other: &Self) -> bool {
+        self.source == other.source
+    }
+}
+
+impl PathPattern {
+    /// Parses a path pattern. A pattern starting with `re:` is a regular expression that can match
+    /// anywhere in the path. Any other pattern is a glob that has to match the whole path, where
+    /// `*` matches any number of characters and `?` matches a single character.
+    pub fn new(source: &str) -> Result<Self, String> {
+        let regex = match source.strip_prefix(REGEX_PREFIX) {
+            Some(regex) => regex.to_string(),
+            None => glob_to_regex(source),
+        };
+        let pattern =
+            Regex::new(&regex).map_err(|err| format!("Invalid pattern `{}`: {}", source, err))?;
+        Ok(PathPattern {
+            source: source.to_string(),
+            pattern,
+        })
+    }
+
+    /// Returns the pattern as it was written.
+    pub fn as_str(&self) -> &str {
+        &self.source
+    }
+
+    /// Returns true if `path` matches the pattern.
+    pub fn is_match(&self, path: &str) -> bool {
+        self.pattern.is_match(path)
+    }
+}
+
+/// Turns a glob into an anchored regular expression.
+fn glob_to_regex(glob: &str) -> String {
+    let mut pattern = String::from("^");
+    for c in glob.chars() {
+        match c {
+            '*' => pattern += ".*",
+            '?' => pattern.push('.'),
+            c => pattern += &regex::escape(&c.to_string()),
+        }
+    }
+    pattern.push('$');
+    pattern
+}
+
+/// Why a candidate request was skipped.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase", tag = "reason", content = "detail")]
+pub enum SkipReason {
+    // The path didn't match any of the include patterns.
+    NotIncluded,
+
+    // The path matched the exclude pattern.
+    Excluded(String),
+
+    // A segment of the path is the dangerous word.
+    Dangerous(String),
+
+    // The request budget was already used up.
+    Budget,
+
+    // A segment of the path is "." or "..", which would leave the base path.
+    DotSegment,
+}
+
+impl fmt::Display for SkipReason {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            SkipReason::NotIncluded => write!(f, "not included"),
+            SkipReason::Excluded(pattern) => write!(f, "excluded by {}", pattern),
+            SkipReason::Dangerous(word) => write!(f, "dangerous ({})", word),
+            SkipReason::Budget => write!(f, "over the request budget"),
+            SkipReason::DotSegment => write!(f, "dot segment"),
+        }
+    }
+}
+
+/// A candidate request that was never sent.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SkippedRequest {
+    pub url: String,
+
+    #[serde(flatten)]
+    pub reason: SkipReason,
+}
+
+/// The paths a scan may request and how many requests it may send.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Scope {
+    // When this isn't empty, only paths matching one of these patterns are requested.
+    pub include: Vec<PathPattern>,
+
+    // Paths matching any of these patterns are never requested.
+    pub exclude: Vec<PathPattern>,
+
+    // Path segments that are never requested. The comparison ignores case and extensions.
+    pub dangerous: Vec<String>,
+
+    // The most requests the scan may send. There is no limit when this isn't set.
+    pub max_requests: Option<usize>,
+}
+
+impl Default for Scope {
+    /// The default scope allows every path except the dangerous ones, with no budget.
+    fn default() -> Self {
+        Scope {
+            include: Vec::new(),
+            exclude: Vec::new(),
+            dangerous: DEFAULT_DANGEROUS
+                .iter()
+                .map(|word| word.to_string())
+                .collect(),
+            max_requests: None,
+        }
+    }
+}
+
+impl Scope {
+    /// Makes a scope that allows every path, with no budget.
+    pub fn unrestricted() -> Self {
+        Scope {
+            dangerous: Vec::new(),
+            ..Default::default()
+        }
+    }
+
+    /// Checks whether `path` may be requested. The budget isn't checked here, since it depends on
+    /// how many requests were already sent.
+    pub fn check(&self, path: &str) -> Result<(), SkipReason> {
+        if let Some(pattern) = self.exclude.iter().find(|pattern| pattern.is_match(path)) {
+            return Err(SkipReason::Excluded(pattern.as_str().to_string()));
+        }
+        if !self.include.is_empty() && !self.include.iter().any(|pattern| pattern.is_match(path)) {
+            return Err(SkipReason::NotIncluded);
+        }
+        if let Some(word) = self.dangerous_word(path) {
+            return Err(SkipReason::Dangerous(word.to_string()));
+        }
+        Ok(())
+    }
+
+    /// Returns the dangerous word matching a segment of `path`, if there is one. A word matches a
+    /// segment that is the word itself, or that starts with the word followed by `.`, `-` or `_`,
+    /// like "logout.php" or "delete_user".
+    pub fn dangerous_word(&self, path: &str) -> Option<&str> {
+        path.split('/').find_map(|segment| {
+            let segment = segment.to_lowercase();
+            self.dangerous
+                .iter()
+                .find(|word| {
+                    let word = word.to_lowercase();
+                    match segment.strip_prefix(word.as_str()) {
+                        Some(rest) => {
+                            rest.is_empty()
+                                || rest.starts_with('.')
+                                || rest.starts_with('-')
+                                || rest.starts_with('_')
+                        }
+                        None => false,
+                    }
+                })
+                .map(|word| word.as_str())
+        })
+    }
+}
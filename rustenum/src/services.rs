@@ -0,0 +1,168 @@
+//! Finding the HTTP services of a host.
+//!
+//! Instead of scanning a single url, a `dir` scan can probe the host of its url on a list of
+//! schemes and ports, like http on 80 and 8080 and https on 443 and 8443. Every service that
+//! answers with any HTTP response is live, and the directory scan is run against each of them in
+//! turn. The base path and query of the url are kept, only the scheme and port are replaced.
+
+use crate::audit::AuditLog;
+use crate::retry::RetryPolicy;
+use crate::scan::{send_probe, Probe};
+use crate::target::Target;
+use futures::future::join_all;
+use reqwest::Client;
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::path::Path;
+use std::time::Duration;
+
+/// Seconds to wait for a service to answer when no timeout is configured. A port that takes a
+/// connection but never answers, like a TLS port probed with plain http, would be waited on forever.
+pub const DEFAULT_PROBE_TIMEOUT_SECS: f64 = 10.0;
+
+/// The services probed when none are configured.
+pub const DEFAULT_SERVICES: [&str; 4] = ["http:80", "https:443", "http:8080", "https:8443"];
+
+/// A scheme and port a host might serve HTTP on.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Service {
+    pub scheme: String,
+    pub port: u16,
+}
+
+impl Service {
+    /// Parses a service written as "scheme:port", like "https:8443". A port on its own, like
+    /// "8080", is probed with both http and https.
+    pub fn parse(text: &str) -> Result<Vec<Self>, String> {
+        let text = text.trim();
+        let (schemes, port) = match text.split_once(':') {
+            Some((scheme, port)) => (vec![scheme.to_lowercase()], port),
+            None => (vec!["http".to_string(), "https".to_string()], text),
+        };
+        let port = match port.parse::<u16>() {
+            Ok(port) if port > 0 => port,
+            _ => return Err(format!("`{}` doesn't have a valid port.", text)),
+        };
+        schemes
+            .into_iter()
+            .map(|scheme| match scheme.as_str() {
+                "http" | "https" => Ok(Service { scheme, port }),
+                _ => Err(format!("`{}` needs to use http or https.", text)),
+            })
+            .collect()
+    }
+
+    /// Returns the url of the service on the host of `target`, with the base path and query of
+    /// the target.
+    pub fn url(&self, target: &Target) -> String {
+        let mut url = target.url().clone();
+        // Switching between http and https can't fail, and both can have a port.
+        let _ = url.set_scheme(&self.scheme);
+        let _ = url.set_port(Some(self.port));
+        url.to_string()
+    }
+
+    /// Returns `path` with the service added to the file name, like "results-https-8443.json",
+    /// so every service scanned gets its own output files.
+    pub fn output_path(&self, path: &str) -> String {
+        let path = Path::new(path);
+        let stem = path
+            .file_stem()
+            .map(|stem| stem.to_string_lossy().to_string())
+            .unwrap_or_default();
+        let mut name = format!("{}-{}-{}", stem, self.scheme, self.port);
+        if let Some(extension) = path.extension() {
+            name += &format!(".{}", extension.to_string_lossy());
+        }
+        path.with_file_name(name).to_string_lossy().to_string()
+    }
+}
+
+impl fmt::Display for Service {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}:{}", self.scheme, self.port)
+    }
+}
+
+/// Parses every service in `list`, leaving out repeats. `DEFAULT_SERVICES` are used when the list
+/// is empty.
+pub fn parse_services(list: &[String]) -> Result<Vec<Service>, String> {
+    let defaults: Vec<String> = DEFAULT_SERVICES.iter().map(|s| s.to_string()).collect();
+    let list = match list.is_empty() {
+        true => &defaults,
+        false => list,
+    };
+    let mut services: Vec<Service> = Vec::new();
+    for text in list {
+        for service in Service::parse(text)? {
+            if !services.contains(&service) {
+                services.push(service);
+            }
+        }
+    }
+    Ok(services)
+}
+
+/// A service that answered with an HTTP response.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct LiveService {
+    pub service: Service,
+
+    // The url the service was probed at, which is the url it is scanned at.
+    pub url: String,
+
+    // The HTTP status code the service answered with.
+    pub status: u16,
+
+    // The value of the `Server` header, if the service sent one.
+    pub server: Option<String>,
+}
+
+/// Sends one request to every service on the host of `target` at the same time, and returns the
+/// services that answered within `timeout`, in the order they were given. Requests aren't
+/// retried, since a service that isn't there would only be waited on again. Every request is
+/// written to the `audit` log, if there is one.
+pub async fn probe_services(
+    client: &Client,
+    target: &Target,
+    services: &[Service],
+    timeout: Duration,
+    audit: Option<&AuditLog>,
+) -> Vec<LiveService> {
+    let policy = RetryPolicy {
+        max_attempts: 1,
+        ..Default::default()
+    };
+    let probes = services.iter().map(|service| {
+        let url = service.url(target);
+        let probe = Probe {
+            name: service.to_string(),
+            url: url.clone(),
+            word: service.to_string(),
+            ..Default::default()
+        };
+        let policy = &policy;
+        async move {
+            let outcome =
+                tokio::time::timeout(timeout, send_probe(client, probe, policy, true, audit))
+                    .await
+                    .ok()?;
+            let result = outcome.result?;
+            Some(LiveService {
+                service: service.clone(),
+                url,
+                status: result.status,
+                server: outcome.head.as_deref().and_then(server_header),
+            })
+        }
+    });
+    join_all(probes).await.into_iter().flatten().collect()
+}
+
+/// Returns the value of the `Server` header from the status line and headers of a response.
+fn server_header(head: &str) -> Option<String> {
+    head.lines()
+        .filter_map(|line| line.split_once(':'))
+        .find(|(name, _)| name.eq_ignore_ascii_case("server"))
+        .map(|(_, value)| value.trim().to_string())
+}
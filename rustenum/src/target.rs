@@ -0,0 +1,124 @@
+//! The target of a scan and how words are joined onto it.
+//!
+//! A `Target` is the base url of a scan. The url keeps everything that was given: the scheme,
+//! the port, the base path and the query string. `http://` is used when no scheme is given, and a
+//! bare IPv6 address like `::1` is put in brackets so it can be used as a host. Each word is joined
+//! onto the base path as one or more path segments, with every character that can't appear in a
+//! path percent-encoded, and the query string of the base url is kept on every request. Words
+//! with a "." or ".." segment are refused, since they would be resolved out of the base path.
+
+use reqwest::Url;
+use std::net::Ipv6Addr;
+
+/// The schemes a scan can be run against.
+const SCHEMES: [&str; 2] = ["http", "https"];
+
+/// The base url of a scan.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Target {
+    url: Url,
+}
+
+impl Target {
+    /// Parses the base url of a scan. `http://` is used when `input` has no scheme. Returns an
+    /// error if the url can't be parsed, or if its scheme isn't http or https.
+    pub fn parse(input: &str) -> Result<Self, String> {
+        let url = Url::parse(&with_scheme(input.trim())).map_err(|_| {
+            "Unable to parse provided url. Please check your `-u`/`--url` argument.".to_string()
+        })?;
+        if !SCHEMES.contains(&url.scheme()) {
+            return Err(format!(
+                "Only http:// and https:// urls can be scanned, not {}://.",
+                url.scheme()
+            ));
+        }
+        if url.host().is_none() {
+            return Err("The url needs a host to scan.".to_string());
+        }
+        Ok(Target { url })
+    }
+
+    /// Returns the parsed base url.
+    pub fn url(&self) -> &Url {
+        &self.url
+    }
+
+    /// Returns the base url as a string.
+    pub fn as_str(&self) -> &str {
+        self.url.as_str()
+    }
+
+    /// Returns the url of `word` under the base path. The base path is treated as a directory
+    /// even if it doesn't end with a '/', and a '/' in the word starts a new path segment. The
+    /// query string of the base url is kept, and any fragment is dropped.
+    ///
+    /// Returns the url as it was written as an error if a segment of the word is a dot segment,
+    /// like ".." or "%2e%2e", since the url would be resolved to a page outside the base path.
+    pub fn join(&self, word: &str) -> Result<String, String> {
+        let mut url = self.url.clone();
+        let mut path = url.path().to_string();
+        if !path.ends_with('/') {
+            path.push('/');
+        }
+        let encoded = encode_path(word.trim_start_matches('/'));
+        path += &encoded;
+        if encoded.split('/').any(is_dot_segment) {
+            let query = url.query().map(|query| format!("?{}", query));
+            return Err(format!(
+                "{}{}{}",
+                url.origin().ascii_serialization(),
+                path,
+                query.unwrap_or_default()
+            ));
+        }
+        url.set_path(&path);
+        url.set_fragment(None);
+        Ok(url.to_string())
+    }
+}
+
+/// Returns `input` with `http://` in front of it if it doesn't start with a scheme. A bare IPv6
+/// address is put in brackets, since it can't be used in a url otherwise.
+pub fn with_scheme(input: &str) -> String {
+    if let Ok(address) = input.parse::<Ipv6Addr>() {
+        return format!("http://[{}]", address);
+    }
+    let has_scheme = input.split_once("://").is_some_and(|(scheme, _)| {
+        scheme.starts_with(|c: char| c.is_ascii_alphabetic())
+            && scheme
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || c == '+' || c == '-' || c == '.')
+    });
+    match has_scheme {
+        true => input.to_string(),
+        false => format!("http://{}", input),
+    }
+}
+
+/// Returns true if `segment` is "." or "..", with any of the dots written as "%2e". Urls resolve
+/// these segments away, so a word made of them can't be requested under the base path.
+pub fn is_dot_segment(segment: &str) -> bool {
+    let decoded = segment.to_ascii_lowercase().replace("%2e", ".");
+    decoded == "." || decoded == ".."
+}
+
+/// Percent-encodes every character of `word` that can't appear in a url path. '/' is kept as the
+/// separator between path segments, and a '%' that already starts an escape like "%41" is kept so
+/// wordlists that are already encoded are sent as they are. Dot segments like "%2e%2e" are still
+/// resolved by the url, which is why `Target::join` refuses them.
+pub fn encode_path(word: &str) -> String {
+    let bytes = word.as_bytes();
+    let mut encoded = String::new();
+    for (index, byte) in bytes.iter().enumerate() {
+        let already_escaped = *byte == b'%'
+            && bytes.len() > index + 2
+            && bytes[index + 1].is_ascii_hexdigit()
+            && bytes[index + 2].is_ascii_hexdigit();
+        if byte.is_ascii_alphanumeric() || b"-._~!$&'()*+,;=:@/".contains(byte) || already_escaped {
+            encoded.push(*byte as char);
+        } else {
+            encoded += &format!("%{:02X}", byte);
+        }
+    }
+    encoded
+}
@@ -0,0 +1,79 @@
+//! Response time statistics and slow endpoint detection.
+//!
+//! The scan engine records how long every response took, including the responses that end up
+//! ignored. Those samples are summarized into latency percentiles, and the median is used as the
+//! baseline for flagging endpoints that are dramatically slower than the rest of the site.
+
+use serde::{Deserialize, Serialize};
+
+/// How many times slower than the median response an endpoint has to be to be flagged as slow,
+/// when no factor is configured.
+pub const DEFAULT_SLOW_FACTOR: f64 = 5.0;
+
+/// An endpoint also has to be at least this many milliseconds slower than the median to be
+/// flagged. This keeps normal jitter on very fast targets, like a server on localhost, from
+/// being reported.
+pub const MIN_SLOW_MARGIN_MS: f64 = 100.0;
+
+/// Percentiles of the total response times of a scan, in milliseconds.
+#[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct LatencySummary {
+    // The number of responses the summary was built from.
+    pub count: usize,
+
+    pub min_ms: f64,
+    pub p50_ms: f64,
+    pub p90_ms: f64,
+    pub p95_ms: f64,
+    pub p99_ms: f64,
+    pub max_ms: f64,
+
+    // Responses that took longer than this are flagged as slow.
+    pub slow_threshold_ms: f64,
+}
+
+/// Returns the `percent` percentile of `sorted`, which has to be sorted in ascending order and
+/// can't be empty. The nearest-rank method is used, so the result is always one of the samples.
+pub fn percentile(sorted: &[f64], percent: f64) -> f64 {
+    let rank = (percent / 100.0 * sorted.len() as f64).ceil() as usize;
+    sorted[rank.clamp(1, sorted.len()) - 1]
+}
+
+impl LatencySummary {
+    /// Summarizes the response times in `samples`. Responses slower than `slow_factor` times the
+    /// median are considered slow. Returns None if there are no samples.
+    pub fn from_samples(samples: &[f64], slow_factor: f64) -> Option<Self> {
+        if samples.is_empty() {
+            return None;
+        }
+
+        let mut sorted = samples.to_vec();
+        sorted.sort_by(|a, b| a.total_cmp(b));
+
+        let median = percentile(&sorted, 50.0);
+        Some(LatencySummary {
+            count: sorted.len(),
+            min_ms: sorted[0],
+            p50_ms: median,
+            p90_ms: percentile(&sorted, 90.0),
+            p95_ms: percentile(&sorted, 95.0),
+            p99_ms: percentile(&sorted, 99.0),
+            max_ms: sorted[sorted.len() - 1],
+            slow_threshold_ms: (median * slow_factor).max(median + MIN_SLOW_MARGIN_MS),
+        })
+    }
+
+    /// Returns true if a response that took `total_ms` milliseconds is dramatically slower than
+    /// the rest of the scan.
+    pub fn is_slow(&self, total_ms: f64) -> bool {
+        total_ms > self.slow_threshold_ms
+    }
+
+    /// Formats the percentiles on a single line for the terminal.
+    pub fn display_line(&self) -> String {
+        format!(
+            "Latency (ms): min {:.1}, p50 {:.1}, p90 {:.1}, p95 {:.1}, p99 {:.1}, max {:.1}",
+            self.min_ms, self.p50_ms, self.p90_ms, self.p95_ms, self.p99_ms, self.max_ms
+        )
+    }
+}
@@ -0,0 +1,144 @@
+//! Building and reshaping wordlists for the `wordlist` subcommand.
+//!
+//! Wordlists can be merged, cleaned of repeats, sorted by how often each word appears, filtered
+//! by a regular expression or by length, and split into chunks of a fixed size. A wordlist can
+//! also be built from the pages that were found by earlier `dir` scans, read from their saved JSON
+//! results, so the paths that keep turning up on real targets are tried first next time.
+
+use crate::report::ScanReport;
+use crate::scan::ScanMode;
+use crate::Wordlist;
+use regex::Regex;
+use reqwest::Url;
+use std::collections::{HashMap, HashSet};
+use std::fs;
+use std::path::Path;
+
+impl Wordlist {
+    /// Reads the wordlist file at `path`, one word per line. Blank lines are left out, and a
+    /// trailing '\r' is removed from every line. Every wordlist is read this way, for scans and
+    /// for the `wordlist` subcommand alike. Returns an error if the file can't be read.
+    pub fn from_file(path: &str) -> Result<Self, String> {
+        let contents = fs::read_to_string(path)
+            .map_err(|err| format!("Could not read the wordlist {}: {}", path, err))?;
+        Ok(Wordlist(
+            contents
+                .lines()
+                .map(|line| line.trim_end_matches('\r'))
+                .filter(|line| !line.is_empty())
+                .map(|line| line.to_string())
+                .collect(),
+        ))
+    }
+
+    /// Builds a wordlist of every page found by the `dir` scans whose JSON results are at `paths`.
+    /// Pages found by several scans are in the wordlist several times, so they can be sorted by
+    /// frequency. Results of other kinds of scans are left out. Returns an error if a results
+    /// file can't be read.
+    pub fn from_results(paths: &[String]) -> Result<Self, String> {
+        let mut wordlist = Wordlist::new();
+        for path in paths {
+            let report = ScanReport::from_json_file(path)?;
+            if report.config.mode != ScanMode::Dir {
+                continue;
+            }
+            for entry in &report.results {
+                // The pages of a scan of several targets are named by their full url.
+                let word = match Url::parse(&entry.path) {
+                    Ok(url) if entry.path.contains("://") => {
+                        url.path().trim_start_matches('/').to_string()
+                    }
+                    _ => entry.path.clone(),
+                };
+                if !word.is_empty() {
+                    wordlist.0.push(word);
+                }
+            }
+        }
+        Ok(wordlist)
+    }
+
+    /// Adds every word of `other` after the words of this wordlist.
+    pub fn merge(&mut self, other: Wordlist) {
+        self.0.extend(other.0);
+    }
+
+    /// Leaves out every repeated word, keeping the first time each word appears.
+    pub fn dedup(&mut self) {
+        let mut seen = HashSet::new();
+        self.0.retain(|word| seen.insert(word.clone()));
+    }
+
+    /// Sorts the words by how many times they appear, most common first, and leaves out the
+    /// repeats. Words that appear equally often stay in the order they first appeared.
+    pub fn sort_by_frequency(&mut self) {
+        let mut counts: HashMap<String, usize> = HashMap::new();
+        for word in &self.0 {
+            *counts.entry(word.clone()).or_default() += 1;
+        }
+        self.dedup();
+        // The sort is stable, so ties keep the order of the words.
+        self.0.sort_by_key(|word| std::cmp::Reverse(counts[word]));
+    }
+
+    /// Keeps only the words `pattern` matches.
+    pub fn retain_matching(&mut self, pattern: &Regex) {
+        self.0.retain(|word| pattern.is_match(word));
+    }
+
+    /// Leaves out the words `pattern` matches.
+    pub fn remove_matching(&mut self, pattern: &Regex) {
+        self.0.retain(|word| !pattern.is_match(word));
+    }
+
+    /// Keeps only the words that are at least `min` and at most `max` characters long. A bound
+    /// that isn't set isn't checked.
+    pub fn retain_lengths(&mut self, min: Option<usize>, max: Option<usize>) {
+        self.0.retain(|word| {
+            let length = word.chars().count();
+            min.is_none_or(|min| length >= min) && max.is_none_or(|max| length <= max)
+        });
+    }
+
+    /// Splits the words into wordlists of `size` words each, in order. The last one has the words
+    /// that are left over.
+    pub fn chunks(&self, size: usize) -> Vec<Wordlist> {
+        self.0
+            .chunks(size.max(1))
+            .map(|chunk| Wordlist(chunk.to_vec()))
+            .collect()
+    }
+
+    /// Writes the words to the file at `path`, one word per line. The file is replaced if it
+    /// exists.
+    pub fn write_to_file(&self, path: &str) -> Result<String, String> {
+        let mut contents = String::new();
+        for word in &self.0 {
+            contents += word;
+            contents += "\n";
+        }
+        match fs::write(path, contents) {
+            Ok(_) => Ok(format!(
+                "Successfully wrote {} words to {}",
+                self.0.len(),
+                path
+            )),
+            Err(_err) => Err("Couldn't write the wordlist to ".to_string() + path),
+        }
+    }
+}
+
+/// Returns the path of the chunk numbered `number` of the wordlist written to `path`, like
+/// "words-2.txt" for the second chunk of "words.txt".
+pub fn chunk_path(path: &str, number: usize) -> String {
+    let path = Path::new(path);
+    let stem = path
+        .file_stem()
+        .map(|stem| stem.to_string_lossy().to_string())
+        .unwrap_or_default();
+    let mut name = format!("{}-{}", stem, number);
+    if let Some(extension) = path.extension() {
+        name += &format!(".{}", extension.to_string_lossy());
+    }
+    path.with_file_name(name).to_string_lossy().to_string()
+}
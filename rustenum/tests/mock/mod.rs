@@ -0,0 +1,375 @@
+//! An in-process mock HTTP server for the end to end scan tests.
+//!
+//! The server listens on a free loopback port and answers every connection with a single
+//! response, chosen by the request's parameters, path or `Host` header. Routes can redirect, answer slowly,
+//! or drop the connection without answering, and every request that arrives is recorded so tests
+//! can check what was sent.
+
+use std::collections::HashMap;
+use std::net::SocketAddr;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::{TcpListener, TcpStream};
+
+/// What the server does when a request matches a route.
+#[derive(Debug, Clone)]
+pub enum Route {
+    // Answer with a response.
+    Respond(MockResponse),
+
+    // Close the connection without answering.
+    Drop,
+
+    // Close the connection without answering for the first `count` requests, then use `then`.
+    DropFirst(usize, Box<Route>),
+
+    // Answer the first `count` requests with `status`, then use `then`.
+    StatusFirst(usize, u16, Box<Route>),
+
+    // Answer with a 200 whose body is the given text followed by the value of the parameter
+    // the route was matched by.
+    Reflect(String),
+
+    // Use the first route for requests whose body contains the text, and the second for the
+    // others.
+    BodyContains(String, Box<Route>, Box<Route>),
+}
+
+/// A response sent by the mock server.
+#[derive(Debug, Clone)]
+pub struct MockResponse {
+    pub status: u16,
+    pub headers: Vec<(String, String)>,
+    pub body: String,
+
+    // How long to wait before answering.
+    pub delay: Option<Duration>,
+}
+
+/// A request received by the mock server.
+#[derive(Debug, Clone, PartialEq)]
+pub struct RecordedRequest {
+    pub method: String,
+
+    // The request target, like "/admin" or "/index.php?id=1".
+    pub path: String,
+
+    // The value of the `Host` header.
+    pub host: Option<String>,
+
+    // Every header, with lowercased names.
+    pub headers: HashMap<String, String>,
+
+    // The parameters from the query string and from a url encoded form body.
+    pub params: HashMap<String, String>,
+
+    // The body as it was sent.
+    pub body: String,
+}
+
+/// Returns a response with `status` and an empty body.
+pub fn status(status: u16) -> Route {
+    body(status, "")
+}
+
+/// Returns a response with `status` and `body`.
+pub fn body(status: u16, body: &str) -> Route {
+    Route::Respond(MockResponse {
+        status,
+        headers: Vec::new(),
+        body: body.to_string(),
+        delay: None,
+    })
+}
+
+/// Returns a redirect with `status` to `location`.
+pub fn redirect(status: u16, location: &str) -> Route {
+    Route::Respond(MockResponse {
+        status,
+        headers: vec![("Location".to_string(), location.to_string())],
+        body: String::new(),
+        delay: None,
+    })
+}
+
+/// Returns a 200 response that is only sent after `delay`.
+pub fn slow(delay: Duration) -> Route {
+    Route::Respond(MockResponse {
+        status: 200,
+        headers: Vec::new(),
+        body: "slow".to_string(),
+        delay: Some(delay),
+    })
+}
+
+#[derive(Default)]
+struct Routes {
+    params: Vec<(String, Route)>,
+    paths: HashMap<String, Route>,
+    hosts: HashMap<String, Route>,
+    fallback: Option<Route>,
+}
+
+/// A mock server that is built up with routes and then started.
+#[derive(Default)]
+pub struct MockServer {
+    routes: Routes,
+}
+
+/// A running mock server.
+pub struct RunningServer {
+    pub address: SocketAddr,
+    requests: Arc<Mutex<Vec<RecordedRequest>>>,
+}
+
+impl MockServer {
+    pub fn new() -> Self {
+        MockServer::default()
+    }
+
+    /// Answers requests for `path` with `route`. A path without a query string also matches
+    /// requests for it that have one.
+    pub fn route(mut self, path: &str, route: Route) -> Self {
+        self.routes.paths.insert(path.to_string(), route);
+        self
+    }
+
+    /// Answers requests that send the parameter `name` with `route`. Parameters are matched before
+    /// paths and hosts, in the order they were added.
+    pub fn param(mut self, name: &str, route: Route) -> Self {
+        self.routes.params.push((name.to_string(), route));
+        self
+    }
+
+    /// Answers requests with a `Host` header of `host` with `route`. Paths are matched first.
+    pub fn vhost(mut self, host: &str, route: Route) -> Self {
+        self.routes.hosts.insert(host.to_string(), route);
+        self
+    }
+
+    /// Answers every request that doesn't match a path or host with `route`. A 404 is sent
+    /// when no fallback is set.
+    pub fn fallback(mut self, route: Route) -> Self {
+        self.routes.fallback = Some(route);
+        self
+    }
+
+    /// Starts the server on a free loopback port in the background.
+    pub async fn start(self) -> RunningServer {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let address = listener.local_addr().unwrap();
+        let requests = Arc::new(Mutex::new(Vec::new()));
+        let routes = Arc::new(Mutex::new(self.routes));
+
+        let recorded = requests.clone();
+        tokio::spawn(async move {
+            while let Ok((stream, _)) = listener.accept().await {
+                let routes = routes.clone();
+                let recorded = recorded.clone();
+                tokio::spawn(async move { handle(stream, routes, recorded).await });
+            }
+        });
+
+        RunningServer { address, requests }
+    }
+}
+
+impl RunningServer {
+    /// The address of the server without a scheme, like "127.0.0.1:4000".
+    pub fn host(&self) -> String {
+        self.address.to_string()
+    }
+
+    /// The base url of the server, like "http://127.0.0.1:4000/".
+    pub fn url(&self) -> String {
+        format!("http://{}/", self.address)
+    }
+
+    /// Every request received so far.
+    pub fn requests(&self) -> Vec<RecordedRequest> {
+        self.requests.lock().unwrap().clone()
+    }
+
+    /// The number of requests received for `path`.
+    pub fn hits(&self, path: &str) -> usize {
+        self.requests()
+            .iter()
+            .filter(|request| request.path == path)
+            .count()
+    }
+}
+
+/// Reads a single request from `stream` and answers it with the matching route.
+async fn handle(
+    mut stream: TcpStream,
+    routes: Arc<Mutex<Routes>>,
+    recorded: Arc<Mutex<Vec<RecordedRequest>>>,
+) {
+    let request = match read_request(&mut stream).await {
+        Some(request) => request,
+        None => return,
+    };
+    recorded.lock().unwrap().push(request.clone());
+
+    // Pick the route and count down the routes that change after a few requests.
+    let response = {
+        let mut routes = routes.lock().unwrap();
+        let routes = &mut *routes;
+        let path = match routes.paths.contains_key(&request.path) {
+            true => request.path.as_str(),
+            false => request.path.split('?').next().unwrap_or_default(),
+        };
+        let param = routes
+            .params
+            .iter_mut()
+            .find(|(name, _)| request.params.contains_key(name));
+        let (value, route) = match param {
+            Some((name, route)) => (request.params.get(name).cloned(), Some(route)),
+            None => match routes.paths.get_mut(path) {
+                Some(route) => (None, Some(route)),
+                None => match request.host.as_ref() {
+                    Some(host) => (None, routes.hosts.get_mut(host)),
+                    None => (None, None),
+                },
+            },
+        };
+        match route.or(routes.fallback.as_mut()) {
+            Some(route) => next_response(route, value.as_deref(), &request.body),
+            None => Some(not_found()),
+        }
+    };
+
+    match response {
+        Some(response) => write_response(stream, &response).await,
+        // Dropping the stream closes the connection without a response.
+        None => drop(stream),
+    }
+}
+
+/// Returns the response for a route, or None if the connection should be dropped. Routes that
+/// change after a number of requests are counted down. `value` is the value of the parameter
+/// the route was matched by, and `body` is the body of the request.
+fn next_response(route: &mut Route, value: Option<&str>, body: &str) -> Option<MockResponse> {
+    match route {
+        Route::Respond(response) => Some(response.clone()),
+        Route::Reflect(text) => Some(MockResponse {
+            status: 200,
+            headers: Vec::new(),
+            body: format!("{}{}", text, value.unwrap_or_default()),
+            delay: None,
+        }),
+        Route::BodyContains(text, matched, other) => match body.contains(text.as_str()) {
+            true => next_response(matched, value, body),
+            false => next_response(other, value, body),
+        },
+        Route::Drop => None,
+        Route::DropFirst(count, then) => {
+            if *count > 0 {
+                *count -= 1;
+                None
+            } else {
+                next_response(then, value, body)
+            }
+        }
+        Route::StatusFirst(count, status, then) => {
+            if *count > 0 {
+                *count -= 1;
+                Some(MockResponse {
+                    status: *status,
+                    headers: Vec::new(),
+                    body: String::new(),
+                    delay: None,
+                })
+            } else {
+                next_response(then, value, body)
+            }
+        }
+    }
+}
+
+fn not_found() -> MockResponse {
+    MockResponse {
+        status: 404,
+        headers: Vec::new(),
+        body: "not found".to_string(),
+        delay: None,
+    }
+}
+
+/// Reads the request line, the headers, and a body if the request has a `Content-Length`.
+async fn read_request(stream: &mut TcpStream) -> Option<RecordedRequest> {
+    let mut buffer = Vec::new();
+    let mut chunk = [0u8; 1024];
+    let head_end = loop {
+        if let Some(index) = buffer.windows(4).position(|window| window == b"\r\n\r\n") {
+            break index + 4;
+        }
+        let read = stream.read(&mut chunk).await.ok()?;
+        if read == 0 {
+            return None;
+        }
+        buffer.extend_from_slice(&chunk[..read]);
+    };
+
+    let head = String::from_utf8_lossy(&buffer[..head_end]).to_string();
+    let mut lines = head.split("\r\n");
+    let mut request_line = lines.next()?.split(' ');
+    let method = request_line.next()?.to_string();
+    let path = request_line.next()?.to_string();
+    let headers: HashMap<String, String> = lines
+        .filter_map(|line| line.split_once(':'))
+        .map(|(name, value)| (name.trim().to_lowercase(), value.trim().to_string()))
+        .collect();
+
+    let length: usize = headers
+        .get("content-length")
+        .and_then(|length| length.parse().ok())
+        .unwrap_or(0);
+    while buffer.len() < head_end + length {
+        let read = stream.read(&mut chunk).await.ok()?;
+        if read == 0 {
+            return None;
+        }
+        buffer.extend_from_slice(&chunk[..read]);
+    }
+    let body = String::from_utf8_lossy(&buffer[head_end..head_end + length]).to_string();
+
+    let mut params = HashMap::new();
+    let query = path.split_once('?').map(|(_, query)| query).unwrap_or("");
+    for pair in query.split('&').chain(body.split('&')) {
+        if let Some((name, value)) = pair.split_once('=') {
+            params.insert(name.to_string(), value.to_string());
+        }
+    }
+
+    Some(RecordedRequest {
+        method,
+        path,
+        host: headers.get("host").cloned(),
+        headers,
+        params,
+        body,
+    })
+}
+
+async fn write_response(mut stream: TcpStream, response: &MockResponse) {
+    if let Some(delay) = response.delay {
+        tokio::time::sleep(delay).await;
+    }
+
+    let mut head = format!(
+        "HTTP/1.1 {} Mock\r\nContent-Length: {}\r\nConnection: close\r\n",
+        response.status,
+        response.body.len()
+    );
+    for (name, value) in &response.headers {
+        head += &format!("{}: {}\r\n", name, value);
+    }
+    head += "\r\n";
+
+    // The client may have given up already, like after a timeout, so errors are ignored.
+    let _ = stream.write_all(head.as_bytes()).await;
+    let _ = stream.write_all(response.body.as_bytes()).await;
+    let _ = stream.shutdown().await;
+}